@@ -0,0 +1,135 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::paths;
+
+/// Named directories to start sessions in
+///
+/// Kept in `bookmarks.toml` in the state directory, and written in the same
+/// format by `--export-bookmarks` so another machine can import them
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Bookmarks {
+    /// directory for each name
+    #[serde(default)]
+    pub bookmarks: BTreeMap<String, String>,
+}
+
+#[derive(Debug)]
+pub enum BookmarksError {
+    /// neither $XDG_STATE_HOME nor $HOME is set
+    NoStateDir,
+    Io(PathBuf, io::Error),
+    Parse(PathBuf, toml::de::Error),
+    Write(toml::ser::Error),
+}
+
+impl fmt::Display for BookmarksError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BookmarksError::NoStateDir => write!(
+                f,
+                "neither $XDG_STATE_HOME nor $HOME is set, don't know where bookmarks go"
+            ),
+            BookmarksError::Io(path, error) => write!(f, "{}: {}", path.display(), error),
+            BookmarksError::Parse(path, error) => {
+                write!(f, "invalid bookmarks in {}: {}", path.display(), error)
+            }
+            BookmarksError::Write(error) => write!(f, "could not write bookmarks: {}", error),
+        }
+    }
+}
+
+/// What importing did with one bookmark from the file
+pub enum Imported {
+    /// it's new here and was added, with its directory
+    Added(String, String),
+    /// the same name and directory were already here
+    Unchanged(String),
+    /// the name is already here for another directory, which was kept
+    Conflict {
+        name: String,
+        kept: String,
+        offered: String,
+    },
+    /// the entry can't be used, and why
+    Invalid(String, String),
+}
+
+/// Where the bookmarks are kept, usually `~/.local/state/tm/bookmarks.toml`
+pub fn bookmarks_path() -> Option<PathBuf> {
+    paths::state_dir().map(|directory| directory.join("bookmarks.toml"))
+}
+
+/// The bookmarks kept on this machine, none if there aren't any yet
+pub fn load() -> Result<Bookmarks, BookmarksError> {
+    let path = bookmarks_path().ok_or(BookmarksError::NoStateDir)?;
+
+    match read(&path) {
+        Err(BookmarksError::Io(_path, error)) if error.kind() == io::ErrorKind::NotFound => {
+            Ok(Bookmarks::default())
+        }
+        result => result,
+    }
+}
+
+/// Keep `bookmarks` as the ones on this machine
+pub fn store(bookmarks: &Bookmarks) -> Result<(), BookmarksError> {
+    let path = bookmarks_path().ok_or(BookmarksError::NoStateDir)?;
+    let path = paths::prepare(path.clone()).map_err(|error| BookmarksError::Io(path, error))?;
+
+    write(&path, bookmarks)
+}
+
+/// Read bookmarks from `file`
+pub fn read(file: &Path) -> Result<Bookmarks, BookmarksError> {
+    let contents =
+        fs::read_to_string(file).map_err(|error| BookmarksError::Io(file.to_path_buf(), error))?;
+
+    toml::from_str(&contents).map_err(|error| BookmarksError::Parse(file.to_path_buf(), error))
+}
+
+/// Write `bookmarks` to `file`
+pub fn write(file: &Path, bookmarks: &Bookmarks) -> Result<(), BookmarksError> {
+    let contents = toml::to_string(bookmarks).map_err(BookmarksError::Write)?;
+
+    fs::write(file, contents).map_err(|error| BookmarksError::Io(file.to_path_buf(), error))
+}
+
+/// Add the bookmarks in `incoming` that `bookmarks` doesn't have yet
+///
+/// A name that's already here keeps its directory. Directories have to be
+/// absolute paths, but needn't exist since the other machine may be set up
+/// differently.
+pub fn merge(bookmarks: &mut Bookmarks, incoming: Bookmarks) -> Vec<Imported> {
+    incoming
+        .bookmarks
+        .into_iter()
+        .map(|(name, path)| {
+            if name.trim().is_empty() {
+                return Imported::Invalid(name, String::from("a bookmark needs a name"));
+            }
+
+            if path.chars().any(char::is_control) || !Path::new(&path).is_absolute() {
+                return Imported::Invalid(name, format!("'{}' isn't an absolute path", path));
+            }
+
+            match bookmarks.bookmarks.get(&name) {
+                Some(kept) if *kept == path => Imported::Unchanged(name),
+                Some(kept) => Imported::Conflict {
+                    kept: kept.clone(),
+                    name,
+                    offered: path,
+                },
+                None => {
+                    bookmarks.bookmarks.insert(name.clone(), path.clone());
+                    Imported::Added(name, path)
+                }
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,1052 @@
+use std::collections::BTreeMap;
+use std::env;
+
+use clap::{Arg, Command};
+
+use crate::config;
+use crate::report;
+
+/// Longer help for a group of related options, shown by `tm --help <topic>`
+///
+/// Each is the topic's name, a one line summary and the full text
+const TOPICS: [(&str, &str, &str); 8] = [
+    (
+        "menu",
+        "picking, creating and editing sessions at the prompt",
+        "At the prompt enter a session number or name to attach to it, n to create a new \
+         session, e<number> to open a session's directory in $EDITOR, r<number> to rename \
+         it, k<number> to kill it, or q to quit. A session named like one of those \
+         answers, e.g. k2, is picked by its name, only n and numbers always mean what they \
+         say. An empty answer picks default_selection when it's set. Any other text \
+         narrows the list down to the sessions whose name contains it, ignoring case, and \
+         the numbers then count in that shorter list. An empty answer shows them all again \
+         when there's no default_selection.\n\n\
+         --sort, --prefix, --grep and --active-only narrow down and order the list, \
+         --prefix is applied before --grep. --loop comes back to the menu after detaching. \
+         Run inside tmux, picking a session switches the current client to it rather than \
+         attaching a tmux within tmux. --select prints the picked session's name instead \
+         of attaching, for shell functions to use. tm attach <name> (or tm a <name>) \
+         attaches to a session without showing the menu. --all-sockets lists the sessions \
+         of every server with a socket in $TMUX_TMPDIR (or /tmp), each tagged with its \
+         socket name.",
+    ),
+    (
+        "new",
+        "how new sessions are named and started",
+        "A new session asks for a directory, which can start with ~ and use $VARIABLES, \
+         then a name suggested from name_template ({dir}, {parent} and {git_root}), or \
+         with name_from_manifest the project name in a Cargo.toml, package.json, \
+         pyproject.toml or the heading of a README.md there. Names can't contain control \
+         characters, and . : or whitespace are turned into _.\n\n\
+         --shell picks the first window's shell, --copy-env copies copy_env_vars into the \
+         session and --preview shows the tmux command and asks before running it. \
+         --from-clipboard takes the directory from the clipboard and --browse <root> lets \
+         you walk down from root to it, picking directories by number.\n\n\
+         --group-with <name> creates a session in a group with an existing one. They share \
+         the same windows, opening or closing one does so in both, but each can show a \
+         different window. --clone-layout copies the windows instead, after which the two \
+         sessions have nothing to do with each other.\n\n\
+         tm new <path> creates a session without asking anything, named as suggested \
+         unless --name is given, and attaches unless --no-attach is. A name that's already \
+         taken is an error.",
+    ),
+    (
+        "kill",
+        "killing and detaching sessions",
+        "--interactive-kill lists the sessions and kills every one whose number is \
+         entered, space separated, after asking to confirm. --detach <name> detaches every \
+         client from a session once it has shown which ones and you've agreed, add \
+         --then-attach to attach to it here instead. --grab (or grab = true) asks the same \
+         before detaching the others when attaching. tm kill <name> kills one session \
+         straight away and tm kill --all stops the tmux server, after asking. \
+         --replace <name> kills a session and starts it again fresh in its directory, or \
+         in the current one if it isn't running unless --must-exist is given.\n\n\
+         Confirmations that can't be undone always default to no and give up after \
+         confirm_timeout seconds when that's set.",
+    ),
+    (
+        "rename",
+        "renaming one or many sessions, notes and session options",
+        "--rename-interactive <name> edits a session's current name in place. \
+         --rename-session-interactive asks for a new name for every session and only \
+         renames once the whole set has been checked, going through a temporary name when \
+         two sessions swap. --rename-from-dir <name> renames a session to the name a new \
+         session in its directory would get, e.g. after the project was moved.\n\n\
+         --note <name> <text> shows text next to a session in the list and \
+         --clear-note <name> removes it. --set-option <name> <option> <value> runs tmux's \
+         set-option for that one session, e.g. --set-option work status off, leaving the \
+         global value and other sessions alone.",
+    ),
+    (
+        "windows",
+        "working with the windows of a session",
+        "--new-window [<session>] adds a window in a directory, to the current session \
+         inside tmux. --select-window-by-name <session> <window> attaches with that window \
+         selected. --link-window shares a window with another session and \
+         --merge <source> <destination> moves all of one session's windows into \
+         another.\n\n\
+         --attach-window-cmd <command> opens a window running command, e.g. htop, in \
+         whichever session is attached to and shows it. --send-keys <name> <keys>... types \
+         into a session, key names like Enter or C-c are pressed. --run <name> <command> \
+         types a command and presses Enter for you, add --new-window to run it in a window \
+         of its own. --capture <name> prints the active pane, --capture-all with its \
+         scrollback.",
+    ),
+    (
+        "layouts",
+        "saving and restoring sessions and templates",
+        "--save-layout <file> writes every session and its windows to a file and \
+         --restore-layout <file> creates the ones that aren't running, showing what will \
+         change first unless --no-preview is given. --dry-run only shows it. --notify \
+         pings you through notify-send, osascript or the terminal bell when it's done.\n\n\
+         --clone-layout <source> <name> starts a session with the same windows and \
+         directories as another, without what was started in them, and attaches to it \
+         unless --no-attach is given.\n\n\
+         --templates-list shows the window setups in the config file's [templates].\n\n\
+         --export-bookmarks <file> writes the bookmarked directories to a file that \
+         --import-bookmarks <file> adds to the bookmarks on another machine. A name that's \
+         already bookmarked there is kept and the clash is shown.",
+    ),
+    (
+        "watch",
+        "keeping an eye on sessions",
+        "--watch redraws the session list every watch_interval seconds until q is pressed. \
+         With --json it prints one JSON object per refresh instead, for other programs to \
+         read. Add --active-only to only see sessions somebody is attached to. --tree \
+         prints each session's windows and what's running in their panes. --spawn <name> \
+         attaches in a new terminal window.",
+    ),
+    (
+        "config",
+        "the config file, environment variables and scripting",
+        "Settings come from built-in defaults, then the config file \
+         (~/.config/tm/config.toml), then TM_* environment variables, then flags. TM_OPTS \
+         holds flags to use every time, e.g. TM_OPTS='--sort mru', the ones on the command \
+         line win over them. The config file's [aliases] table gives short names to sets \
+         of flags, e.g. w = \"--watch --active-only\" makes tm w the same as tm --watch \
+         --active-only. --init writes a config file with every setting explained and \
+         --dump-config shows what's in effect and where each value came from. --which \
+         shows the tmux binary tmux_bin ends up running and its version, --health checks \
+         tmux, the config file, the state directory and the environment and says what's \
+         missing, without changing anything. With record_errors on, the last tmux command \
+         that failed is kept along with its exit code and what it printed, \
+         --dump-last-error shows it. -v says on stderr which tmux was found, -vv also \
+         which format sessions are listed with.\n\n\
+         For scripts: --no-interactive fails instead of asking, --list-names prints just \
+         the names (NUL separated with -0, for xargs -0), --pipe-to hands the list to a \
+         picker like fzf and --error-format json writes errors as JSON. Output is only \
+         colored on a terminal and without NO_COLOR set, --color always or never overrides \
+         both. --quiet leaves out the \"Wrote\", \"Detached\", \"Saved\", \"Killed\" and \
+         \"Created\"/\"Skipped\" lines of --init, --detach, --save-layout, \
+         --restore-layout, tm new and tm kill, the --restore-layout preview is still shown \
+         unless --no-preview is given.",
+    ),
+];
+
+/// Build the command line definition for `tm`
+pub fn build() -> Command<'static> {
+    Command::new("tm")
+        .version(env!("CARGO_PKG_VERSION"))
+        .about("List tmux sessions and attach to the one you pick")
+        .after_help(
+            "At the prompt enter a session number or name to attach to it, n to create a \
+             new session, e<number> to open a session's directory in $EDITOR, r<number> to \
+             rename it, k<number> to kill it, or q to quit.\n\n\
+             Answers can be piped in, one per line, in the order the prompts would \
+             appear. To create a session that is: n, the directory, then the session \
+             name (an empty line keeps the suggested one), e.g.\n\n    \
+             printf 'n\\n%s\\n\\n' \"$PWD\" | tm\n\n\
+             Use --help <topic> for more on any of the topics below.",
+        )
+        // replaced by our own --help that also takes a topic
+        .disable_help_flag(true)
+        // so a flag given on the command line wins over the same one in TM_OPTS
+        .args_override_self(true)
+        .arg(
+            Arg::new("help")
+                .short('h')
+                .long("help")
+                .takes_value(true)
+                .min_values(0)
+                .value_name("TOPIC")
+                .help("Print help, or the help for one topic"),
+        )
+        .arg(
+            Arg::new("dump-config")
+                .long("dump-config")
+                .help("Print the resolved configuration as JSON and exit"),
+        )
+        .arg(
+            Arg::new("dump-last-error")
+                .long("dump-last-error")
+                .help("Print the last tmux command that failed, kept when record_errors is on, and exit"),
+        )
+        .arg(
+            Arg::new("health")
+                .long("health")
+                .help("Check tmux, the config file and the environment for problems and exit"),
+        )
+        .arg(
+            Arg::new("which")
+                .long("which")
+                .help("Print where the tmux that will be run is and its version, then exit"),
+        )
+        .arg(
+            Arg::new("init")
+                .long("init")
+                .help("Write a config file with every setting at its default and exit"),
+        )
+        .arg(
+            Arg::new("force")
+                .long("force")
+                .requires("init")
+                .help("Overwrite an existing config file with --init"),
+        )
+        .arg(
+            Arg::new("sort")
+                .long("sort")
+                .takes_value(true)
+                .value_name("ORDER")
+                .possible_values(["name", "mru", "windows"])
+                .help("Order sessions by name, most recently used, or window count"),
+        )
+        .arg(
+            Arg::new("grep")
+                .long("grep")
+                .takes_value(true)
+                .value_name("PATTERN")
+                .help("Only list sessions whose name matches the regular expression"),
+        )
+        .arg(
+            Arg::new("prefix")
+                .long("prefix")
+                .takes_value(true)
+                .value_name("PREFIX")
+                .help("Only list sessions whose name starts with PREFIX, applied before --grep"),
+        )
+        .arg(
+            Arg::new("active-only")
+                .long("active-only")
+                .help("Only list sessions that have a client attached"),
+        )
+        .arg(
+            Arg::new("name-template")
+                .long("name-template")
+                .takes_value(true)
+                .value_name("TEMPLATE")
+                .help(
+                    "How to name new sessions from their directory, using {dir}, \
+                     {parent} and {git_root} [default: {dir}]",
+                ),
+        )
+        .arg(
+            Arg::new("copy-env")
+                .long("copy-env")
+                .help("Give a new session the values copy_env_vars have here [default: PATH, LANG, SSH_AUTH_SOCK]"),
+        )
+        .arg(
+            Arg::new("grab")
+                .long("grab")
+                .help("Detach any other clients when attaching so the session fits this terminal"),
+        )
+        .arg(
+            Arg::new("preview")
+                .long("preview")
+                .help("Show the tmux command that will create a new session and ask before running it"),
+        )
+        .arg(
+            Arg::new("size")
+                .long("size")
+                .takes_value(true)
+                .value_name("WxH")
+                .help("Resize the session's windows to W columns by H lines before attaching"),
+        )
+        .arg(
+            Arg::new("attach-window-cmd")
+                .long("attach-window-cmd")
+                .takes_value(true)
+                .value_name("COMMAND")
+                .help("Open a new window running COMMAND in the session just before attaching, and show it"),
+        )
+        .arg(
+            Arg::new("shell")
+                .long("shell")
+                .takes_value(true)
+                .value_name("PATH")
+                .help("Shell the first window of a new session runs instead of tmux's default-command"),
+        )
+        .arg(
+            Arg::new("pipe-to")
+                .long("pipe-to")
+                .takes_value(true)
+                .value_name("COMMAND")
+                .help("Pick the session with COMMAND (e.g. fzf), which reads the names on stdin"),
+        )
+        .arg(
+            Arg::new("list-names")
+                .long("list-names")
+                .help("Print the session names one per line and exit"),
+        )
+        .arg(
+            Arg::new("print0")
+                .short('0')
+                .long("print0")
+                .requires("list-names")
+                .conflicts_with("json")
+                .help("End each name from --list-names with a NUL byte instead of a newline"),
+        )
+        .arg(
+            Arg::new("select")
+                .long("select")
+                .help("Pick a session and print its name instead of attaching, prompts go to stderr"),
+        )
+        .arg(
+            Arg::new("loop")
+                .long("loop")
+                .help("Come back to the menu after detaching from a session, until q is entered"),
+        )
+        .arg(
+            Arg::new("watch")
+                .long("watch")
+                .help("Keep showing the sessions, refreshed every watch_interval seconds, until q"),
+        )
+        .arg(
+            Arg::new("interactive-kill")
+                .long("interactive-kill")
+                .help("Pick several sessions by number and kill them after one confirmation"),
+        )
+        .arg(
+            Arg::new("detach")
+                .long("detach")
+                .takes_value(true)
+                .value_name("NAME")
+                .help(
+                    "Detach every client from session NAME and exit, \
+                     including other people's terminals",
+                ),
+        )
+        .arg(
+            Arg::new("then-attach")
+                .long("then-attach")
+                .requires("detach")
+                .help("With --detach, attach to the session once everyone else is off it"),
+        )
+        .arg(
+            Arg::new("all-sockets")
+                .long("all-sockets")
+                .help("List the sessions of every tmux server found, tagged by socket, and attach through the right one"),
+        )
+        .arg(
+            Arg::new("replace")
+                .long("replace")
+                .takes_value(true)
+                .value_name("NAME")
+                .help("Kill session NAME and start it again fresh in the same directory, then attach"),
+        )
+        .arg(
+            Arg::new("must-exist")
+                .long("must-exist")
+                .requires("replace")
+                .help("Fail with --replace when the session isn't running instead of starting it here"),
+        )
+        .arg(
+            Arg::new("group-with")
+                .long("group-with")
+                .takes_value(true)
+                .value_name("NAME")
+                .help("Create a session sharing the windows of session NAME and attach to it"),
+        )
+        .arg(
+            Arg::new("browse")
+                .long("browse")
+                .takes_value(true)
+                .value_name("ROOT")
+                .help("Create a session in a directory picked by walking down from ROOT"),
+        )
+        .arg(
+            Arg::new("from-clipboard")
+                .long("from-clipboard")
+                .help("Create a session for the directory path on the clipboard"),
+        )
+        .arg(
+            Arg::new("rename-interactive")
+                .long("rename-interactive")
+                .takes_value(true)
+                .value_name("NAME")
+                .help("Rename a session, editing its current name"),
+        )
+        .arg(
+            Arg::new("rename-from-dir")
+                .long("rename-from-dir")
+                .takes_value(true)
+                .value_name("NAME")
+                .help("Rename a session to the name suggested from its directory, after asking"),
+        )
+        .arg(
+            Arg::new("rename-session-interactive")
+                .long("rename-session-interactive")
+                .help("Rename any number of sessions in one go, each one is asked about in turn"),
+        )
+        .arg(
+            Arg::new("note")
+                .long("note")
+                .number_of_values(2)
+                .value_names(&["NAME", "TEXT"])
+                .help("Set the note shown next to a session in the list"),
+        )
+        .arg(
+            Arg::new("clear-note")
+                .long("clear-note")
+                .takes_value(true)
+                .value_name("NAME")
+                .conflicts_with("note")
+                .help("Remove a session's note"),
+        )
+        .arg(
+            Arg::new("select-window-by-name")
+                .long("select-window-by-name")
+                .number_of_values(2)
+                .value_names(&["SESSION", "WINDOW"])
+                .help("Attach to a session with the window of that name selected"),
+        )
+        .arg(
+            Arg::new("capture")
+                .long("capture")
+                .takes_value(true)
+                .value_name("NAME")
+                .help("Print what's on screen in a session's active pane"),
+        )
+        .arg(
+            Arg::new("capture-all")
+                .long("capture-all")
+                .requires("capture")
+                .help("Include the pane's whole scrollback with --capture"),
+        )
+        .arg(
+            Arg::new("new-window")
+                .long("new-window")
+                .takes_value(true)
+                .min_values(0)
+                .value_name("SESSION")
+                .help("Add a window in a directory to a session, the current one inside tmux"),
+        )
+        .arg(
+            Arg::new("send-keys")
+                .long("send-keys")
+                .min_values(2)
+                .value_name("NAME KEYS")
+                .allow_hyphen_values(true)
+                .help(
+                    "Type KEYS into session NAME without attaching, each one is handed to tmux \
+                     send-keys as is so key names like Enter or C-c are pressed rather than typed",
+                ),
+        )
+        .arg(
+            Arg::new("set-option")
+                .long("set-option")
+                .number_of_values(3)
+                .value_names(&["NAME", "OPTION", "VALUE"])
+                .allow_hyphen_values(true)
+                .help("Set a tmux session option like status or mouse for session NAME only"),
+        )
+        .arg(
+            Arg::new("run")
+                .long("run")
+                .number_of_values(2)
+                .value_names(&["NAME", "COMMAND"])
+                .allow_hyphen_values(true)
+                .help("Type COMMAND into a session's active pane and press Enter, in a new window with --new-window"),
+        )
+        .arg(
+            Arg::new("spawn")
+                .long("spawn")
+                .takes_value(true)
+                .value_name("NAME")
+                .help("Open a new terminal window attached to a session, using $TERMINAL or the terminal setting"),
+        )
+        .arg(
+            Arg::new("tree")
+                .long("tree")
+                .help("Print every session with its windows and their panes, indented, and exit"),
+        )
+        .arg(
+            Arg::new("merge")
+                .long("merge")
+                .number_of_values(2)
+                .value_names(&["SOURCE", "DESTINATION"])
+                .help("Move every window of SOURCE into DESTINATION, which ends SOURCE"),
+        )
+        .arg(
+            Arg::new("link-window")
+                .long("link-window")
+                .help("Pick a window and a second session for it to also appear in"),
+        )
+        .arg(
+            Arg::new("session-info")
+                .long("session-info")
+                .takes_value(true)
+                .value_name("NAME")
+                .help("Print the details of session NAME and exit"),
+        )
+        .arg(
+            Arg::new("no-interactive")
+                .long("no-interactive")
+                .help("Fail instead of prompting whenever an answer would be needed"),
+        )
+        .arg(
+            Arg::new("error-format")
+                .long("error-format")
+                .takes_value(true)
+                .value_name("FORMAT")
+                .possible_values(["text", "json"])
+                .default_value("text")
+                .help("Print errors as plain text or as a JSON object with the exit code"),
+        )
+        .arg(
+            Arg::new("quiet")
+                .short('q')
+                .long("quiet")
+                .help(
+                    "Only print errors from --init, --detach, --save-layout, --restore-layout, \
+                     tm new and tm kill, not what they did",
+                ),
+        )
+        .arg(
+            Arg::new("verbose")
+                .short('v')
+                .long("verbose")
+                .multiple_occurrences(true)
+                .help(
+                    "Say on stderr which tmux was found, twice (-vv) also which format \
+                     sessions are listed with",
+                ),
+        )
+        .arg(
+            Arg::new("color")
+                .long("color")
+                .takes_value(true)
+                .value_name("WHEN")
+                .possible_values(["always", "auto", "never"])
+                .help(
+                    "Color output always, never, or only on a terminal without NO_COLOR set, \
+                     always and never win over NO_COLOR [default: auto]",
+                ),
+        )
+        .arg(
+            Arg::new("no-color")
+                .long("no-color")
+                .conflicts_with("color")
+                .help("Don't color output, the same as --color never"),
+        )
+        .arg(
+            Arg::new("json")
+                .long("json")
+                .help("Print machine readable JSON instead of text where supported"),
+        )
+        .arg(
+            Arg::new("templates-list")
+                .long("templates-list")
+                .help("Print the session templates defined in the config file and exit"),
+        )
+        .arg(
+            Arg::new("export-bookmarks")
+                .long("export-bookmarks")
+                .takes_value(true)
+                .value_name("FILE")
+                .help("Write the bookmarked directories to FILE to take to another machine and exit"),
+        )
+        .arg(
+            Arg::new("import-bookmarks")
+                .long("import-bookmarks")
+                .takes_value(true)
+                .value_name("FILE")
+                .conflicts_with("export-bookmarks")
+                .help("Add the bookmarks in FILE that aren't here yet and exit"),
+        )
+        .arg(
+            Arg::new("save-layout")
+                .long("save-layout")
+                .takes_value(true)
+                .value_name("FILE")
+                .help("Save every session, its windows and their directories to FILE and exit"),
+        )
+        .arg(
+            Arg::new("restore-layout")
+                .long("restore-layout")
+                .takes_value(true)
+                .value_name("FILE")
+                .conflicts_with("save-layout")
+                .help("Recreate the sessions saved in FILE that aren't running and exit"),
+        )
+        .arg(
+            Arg::new("notify")
+                .long("notify")
+                .requires("restore-layout")
+                .help("Show a desktop notification, or ring the bell, once --restore-layout is done"),
+        )
+        .arg(
+            Arg::new("clone-layout")
+                .long("clone-layout")
+                .number_of_values(2)
+                .value_names(&["SOURCE", "NAME"])
+                .help(
+                    "Start session NAME with the same windows and directories as SOURCE, \
+                     what's running in them isn't copied",
+                ),
+        )
+        .arg(
+            Arg::new("no-attach")
+                .long("no-attach")
+                .requires("clone-layout")
+                .help("Leave the session --clone-layout starts detached"),
+        )
+        .arg(
+            Arg::new("dry-run")
+                .long("dry-run")
+                .help("Show what would be done without changing anything"),
+        )
+        .arg(
+            Arg::new("no-preview")
+                .long("no-preview")
+                .requires("restore-layout")
+                .help("Don't show what --restore-layout is about to change first"),
+        )
+        // `tm help` would only be another way to say --help
+        .disable_help_subcommand(true)
+        .subcommand(
+            Command::new("attach")
+                .alias("a")
+                .about("Attach to a session by name, without the menu")
+                .arg(
+                    Arg::new("name")
+                        .index(1)
+                        .required(true)
+                        .value_name("NAME")
+                        .help("The session to attach to"),
+                ),
+        )
+        .subcommand(
+            Command::new("kill")
+                .about("Kill a session by name, or every session with --all")
+                .arg(
+                    Arg::new("name")
+                        .index(1)
+                        .required_unless_present("all")
+                        .conflicts_with("all")
+                        .value_name("NAME")
+                        .help("The session to kill"),
+                )
+                .arg(
+                    Arg::new("all")
+                        .long("all")
+                        .help("Kill the tmux server and with it every session, after asking"),
+                ),
+        )
+        .subcommand(
+            Command::new("new")
+                .about("Create a session in a directory without asking anything, then attach")
+                .arg(
+                    Arg::new("path")
+                        .index(1)
+                        .required(true)
+                        .value_name("PATH")
+                        .help("The directory the session starts in"),
+                )
+                .arg(
+                    Arg::new("name")
+                        .long("name")
+                        .takes_value(true)
+                        .value_name("NAME")
+                        .help("What to call the session instead of the name suggested from PATH"),
+                )
+                .arg(
+                    Arg::new("no-attach")
+                        .long("no-attach")
+                        .help("Only create the session"),
+                ),
+        )
+}
+
+/// The command line to parse, with the flags in `TM_OPTS` in front of the real ones
+///
+/// Coming first means anything given on the command line overrides them. A
+/// first argument that's one of the config file's `aliases` is replaced by
+/// what it stands for. Exits when `TM_OPTS` can't be split up.
+pub fn args() -> Vec<String> {
+    let mut args = env::args();
+    let mut all: Vec<String> = args.next().into_iter().collect();
+
+    if let Some(opts) = env::var_os("TM_OPTS") {
+        match split_words(&opts.to_string_lossy()) {
+            Ok(words) => all.extend(words),
+            Err(error) => report::fail(format!("invalid TM_OPTS: {}", error), 2),
+        }
+    }
+
+    let args: Vec<String> = args.collect();
+
+    // a broken config file is reported once it's loaded properly, after parsing
+    let aliases = config::load()
+        .map(|resolved| resolved.config.aliases)
+        .unwrap_or_default();
+
+    all.extend(expand_alias(args, &aliases));
+    all
+}
+
+/// Whether `args` or `TM_OPTS` ask for `--error-format json`, before either is parsed
+///
+/// Only the plain spellings are recognised, `--error-format=json` and
+/// `--error-format json`. Parsing sees every other one.
+pub fn wants_json_errors(mut args: Vec<String>) -> bool {
+    if let Some(opts) = env::var_os("TM_OPTS") {
+        args.extend(opts.to_string_lossy().split_whitespace().map(String::from));
+    }
+
+    // whichever is given last wins, like it does once they're parsed
+    let mut json = false;
+
+    for (index, arg) in args.iter().enumerate() {
+        match arg.strip_prefix("--error-format") {
+            Some(value) if value.starts_with('=') => json = value == "=json",
+            Some("") => json = args.get(index + 1).map(String::as_str) == Some("json"),
+            _ => (),
+        }
+    }
+
+    json
+}
+
+/// Stop because the command line couldn't be parsed, as JSON when that's what errors are
+///
+/// Help and the version aren't errors and are printed by clap as usual
+pub fn exit_with(error: clap::Error) -> ! {
+    if !error.use_stderr() || !report::is_json() {
+        error.exit();
+    }
+
+    // the rest is usage and the tip about --help, which a program has no use for
+    let message = error.to_string();
+    let message = message.lines().next().unwrap_or_default().trim();
+
+    report::fail(message, 2);
+}
+
+/// `args` with the first one replaced by its expansion when it's one of `aliases`
+///
+/// An alias can stand for any number of flags, split up like `TM_OPTS`.
+/// Names starting with `-` would hide tm's own flags and names like `attach`
+/// or `a` its subcommands, so they're ignored.
+fn expand_alias(mut args: Vec<String>, aliases: &BTreeMap<String, String>) -> Vec<String> {
+    let first = match args.first() {
+        Some(first) => first,
+        None => return args,
+    };
+
+    for warning in alias_warnings(aliases) {
+        report::error(warning);
+    }
+
+    let expansion = match aliases.get(first) {
+        Some(expansion) if hides(first).is_none() => expansion,
+        _ => return args,
+    };
+
+    match split_words(expansion) {
+        Ok(words) => {
+            args.splice(0..1, words);
+        }
+        Err(error) => report::fail(format!("invalid alias '{}': {}", first, error), 2),
+    }
+
+    args
+}
+
+/// A warning for each of `aliases` that's ignored, naming it and what it would hide
+fn alias_warnings(aliases: &BTreeMap<String, String>) -> Vec<String> {
+    aliases
+        .keys()
+        .filter_map(|name| {
+            hides(name).map(|hidden| {
+                format!(
+                    "ignoring the alias '{}', it would hide the {} of the same name",
+                    name, hidden
+                )
+            })
+        })
+        .collect()
+}
+
+/// What of tm's own an alias called `name` would hide, if anything
+fn hides(name: &str) -> Option<&'static str> {
+    if name.starts_with('-') {
+        return Some("flag");
+    }
+
+    build()
+        .get_subcommands()
+        .any(|subcommand| {
+            subcommand.get_name() == name || subcommand.get_all_aliases().any(|alias| alias == name)
+        })
+        .then_some("subcommand")
+}
+
+/// Split `input` into words the way a shell would, without expanding anything
+///
+/// Words are separated by whitespace. Single quotes keep everything inside
+/// as is, double quotes too apart from `\"` and `\\`, and outside of quotes
+/// a backslash keeps the next character as it is.
+fn split_words(input: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = input.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                let word = word.get_or_insert_with(String::new);
+
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => word.push(c),
+                        None => return Err(String::from("a ' isn't closed")),
+                    }
+                }
+            }
+            '"' => {
+                let word = word.get_or_insert_with(String::new);
+
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\')) => word.push(c),
+                            Some(c) => {
+                                word.push('\\');
+                                word.push(c);
+                            }
+                            None => return Err(String::from("a \" isn't closed")),
+                        },
+                        Some(c) => word.push(c),
+                        None => return Err(String::from("a \" isn't closed")),
+                    }
+                }
+            }
+            '\\' => match chars.next() {
+                Some(c) => word.get_or_insert_with(String::new).push(c),
+                None => return Err(String::from("it ends with a \\")),
+            },
+            c if c.is_whitespace() => words.extend(word.take()),
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+
+    words.extend(word);
+
+    Ok(words)
+}
+
+/// `--help [<topic>]`
+pub fn help(topic: Option<&str>) {
+    let topic = match topic {
+        Some(topic) => topic,
+        None => {
+            if let Err(error) = build().print_help() {
+                report::fail(format!("could not print the help: {}", error), 1);
+            }
+
+            println!();
+            println!("TOPICS:");
+            TOPICS
+                .iter()
+                .for_each(|(name, summary, _text)| println!("    {:<10}{}", name, summary));
+
+            return;
+        }
+    };
+
+    match TOPICS.iter().find(|(name, _summary, _text)| *name == topic) {
+        Some((_name, _summary, text)) => println!("{}", text),
+        None => {
+            let names: Vec<&str> = TOPICS.iter().map(|(name, _summary, _text)| *name).collect();
+
+            report::fail(
+                format!(
+                    "there's no help topic '{}', try one of: {}",
+                    topic,
+                    names.join(", ")
+                ),
+                2,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn send_keys_takes_a_name_and_several_keys() {
+        let matches = build()
+            .try_get_matches_from(["tm", "--send-keys", "work", "git pull", "Enter"])
+            .unwrap();
+        let values: Vec<&str> = matches.values_of("send-keys").unwrap().collect();
+
+        assert_eq!(values, ["work", "git pull", "Enter"]);
+    }
+
+    #[test]
+    fn send_keys_needs_some_keys() {
+        assert!(build()
+            .try_get_matches_from(["tm", "--send-keys", "work"])
+            .is_err());
+    }
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn wants_json_errors_finds_both_spellings() {
+        assert!(wants_json_errors(args(&[
+            "tm",
+            "--error-format=json",
+            "--bogus"
+        ])));
+        assert!(wants_json_errors(args(&[
+            "tm",
+            "--bogus",
+            "--error-format",
+            "json"
+        ])));
+    }
+
+    #[test]
+    fn wants_json_errors_goes_by_the_last_one() {
+        assert!(!wants_json_errors(args(&[
+            "tm",
+            "--error-format=json",
+            "--error-format=text"
+        ])));
+        assert!(!wants_json_errors(args(&["tm", "--error-format"])));
+        assert!(!wants_json_errors(args(&["tm", "--list"])));
+    }
+
+    #[test]
+    fn split_words_splits_on_whitespace() {
+        assert_eq!(
+            split_words("  --sort  mru\t-q "),
+            Ok(args(&["--sort", "mru", "-q"]))
+        );
+        assert_eq!(split_words(""), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn split_words_keeps_quoted_spaces() {
+        assert_eq!(
+            split_words("--prefix 'my work' --grep \"a b\""),
+            Ok(args(&["--prefix", "my work", "--grep", "a b"]))
+        );
+        assert_eq!(split_words("''"), Ok(args(&[""])));
+        assert_eq!(split_words("a'b c'd"), Ok(args(&["ab cd"])));
+    }
+
+    #[test]
+    fn split_words_follows_escapes() {
+        assert_eq!(split_words("a\\ b"), Ok(args(&["a b"])));
+        assert_eq!(split_words("\"say \\\"hi\\\"\""), Ok(args(&["say \"hi\""])));
+        assert_eq!(split_words("\"a\\nb\""), Ok(args(&["a\\nb"])));
+        assert_eq!(split_words("'a\\b'"), Ok(args(&["a\\b"])));
+    }
+
+    #[test]
+    fn split_words_refuses_what_isnt_closed() {
+        assert_eq!(split_words("'mru"), Err(String::from("a ' isn't closed")));
+        assert_eq!(split_words("\"mru"), Err(String::from("a \" isn't closed")));
+        assert_eq!(split_words("mru\\"), Err(String::from("it ends with a \\")));
+    }
+
+    fn aliases(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs
+            .iter()
+            .map(|(name, expansion)| (name.to_string(), expansion.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn expand_alias_replaces_a_single_token() {
+        let aliases = aliases(&[("ls", "--list-names")]);
+
+        assert_eq!(
+            expand_alias(args(&["ls", "-0"]), &aliases),
+            args(&["--list-names", "-0"])
+        );
+    }
+
+    #[test]
+    fn expand_alias_replaces_one_word_with_several() {
+        let aliases = aliases(&[("recent", "--sort mru --prefix 'my work'")]);
+
+        assert_eq!(
+            expand_alias(args(&["recent", "--loop"]), &aliases),
+            args(&["--sort", "mru", "--prefix", "my work", "--loop"])
+        );
+    }
+
+    #[test]
+    fn expand_alias_only_looks_at_the_first_argument() {
+        let aliases = aliases(&[("ls", "--list-names")]);
+
+        assert_eq!(
+            expand_alias(args(&["--grep", "ls"]), &aliases),
+            args(&["--grep", "ls"])
+        );
+        assert!(expand_alias(Vec::new(), &aliases).is_empty());
+    }
+
+    #[test]
+    fn expand_alias_ignores_aliases_named_like_flags() {
+        let aliases = aliases(&[("-q", "--list"), ("--list", "--sort mru")]);
+
+        assert_eq!(expand_alias(args(&["-q"]), &aliases), args(&["-q"]));
+        assert_eq!(expand_alias(args(&["--list"]), &aliases), args(&["--list"]));
+    }
+
+    #[test]
+    fn expand_alias_ignores_aliases_named_like_subcommands() {
+        let aliases = aliases(&[("attach", "--list"), ("a", "--list"), ("new", "--list")]);
+
+        assert_eq!(
+            expand_alias(args(&["attach", "work"]), &aliases),
+            args(&["attach", "work"])
+        );
+        assert_eq!(
+            expand_alias(args(&["a", "work"]), &aliases),
+            args(&["a", "work"])
+        );
+        assert_eq!(expand_alias(args(&["new"]), &aliases), args(&["new"]));
+    }
+
+    #[test]
+    fn alias_warnings_name_the_alias_and_what_it_hides() {
+        let aliases = aliases(&[("kill", "--list"), ("-q", "--list"), ("ls", "--list-names")]);
+
+        assert_eq!(
+            alias_warnings(&aliases),
+            [
+                "ignoring the alias '-q', it would hide the flag of the same name",
+                "ignoring the alias 'kill', it would hide the subcommand of the same name",
+            ]
+        );
+    }
+
+    #[test]
+    fn hides_says_what_an_alias_would_hide() {
+        assert_eq!(hides("-q"), Some("flag"));
+        assert_eq!(hides("kill"), Some("subcommand"));
+        assert_eq!(hides("a"), Some("subcommand"));
+        assert_eq!(hides("ls"), None);
+    }
+}
@@ -0,0 +1,46 @@
+use std::io;
+
+// tried in order, the first one that's installed and works wins
+// wl-paste: Wayland, xclip and xsel: X11, pbpaste: macOS
+const TOOLS: [(&str, &[&str]); 4] = [
+    ("wl-paste", &["--no-newline"]),
+    ("xclip", &["-selection", "clipboard", "-o"]),
+    ("xsel", &["--clipboard", "--output"]),
+    ("pbpaste", &[]),
+];
+
+/// The text on the system clipboard
+///
+/// Shells out to whichever clipboard tool is available. When none are
+/// installed the error is `NotFound`, otherwise it's why the last one failed.
+pub fn read() -> io::Result<String> {
+    let mut failure = io::Error::new(
+        io::ErrorKind::NotFound,
+        "no clipboard tool found, install wl-paste, xclip, xsel or pbpaste",
+    );
+
+    for (command, args) in TOOLS {
+        let app = scuttle::App {
+            command: command.to_string(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+        };
+
+        match scuttle::run_output(&app) {
+            Ok(output) if output.status.success() => {
+                return Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+            }
+            // installed but not usable here, e.g. xclip without an X display
+            Ok(output) => {
+                failure = io::Error::other(format!(
+                    "{}: {}",
+                    command,
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ))
+            }
+            Err(error) if error.kind() == io::ErrorKind::NotFound => (),
+            Err(error) => failure = io::Error::other(format!("{}: {}", command, error)),
+        }
+    }
+
+    Err(failure)
+}
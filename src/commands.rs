@@ -0,0 +1,1097 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::io::Write;
+use std::path::Path;
+use std::process;
+
+use serde::Serialize;
+
+use crate::bookmarks::{self, Imported};
+use crate::config;
+use crate::fs_utils;
+use crate::info;
+use crate::layout::{self, Change, Restored};
+use crate::paths;
+use crate::prompt;
+use crate::report;
+use crate::session_utils;
+use crate::sessions::SessionList;
+use crate::term::{self, Color};
+use crate::tmux::{self, LinkWindowError, NewSessionError};
+use crate::tree;
+
+/// Print `value` as pretty JSON
+pub fn print_json<T: Serialize>(value: &T) {
+    match serde_json::to_string_pretty(value) {
+        Ok(json) => println!("{}", json),
+        Err(error) => report::fail(format!("could not write JSON: {}", error), 1),
+    };
+}
+
+/// Stop quietly if whatever we're writing to has gone away
+///
+/// A reader like `head` closing the pipe early isn't an error, it just means it
+/// has seen enough. Any other failure to write is.
+pub fn exit_on_broken_pipe(result: io::Result<()>) {
+    match result {
+        Ok(()) => (),
+        Err(error) if error.kind() == io::ErrorKind::BrokenPipe => process::exit(0),
+        Err(error) => report::fail(format!("could not write the output: {}", error), 1),
+    }
+}
+
+/// `--list-names [--print0]`, written to `out`
+pub fn list_names(out: &mut dyn Write, sessions: &SessionList, print0: bool) {
+    // NUL can't be in a name, so unlike a newline it always ends one (for `xargs -0`)
+    let end = if print0 { '\0' } else { '\n' };
+
+    for name in sessions.names() {
+        exit_on_broken_pipe(write!(out, "{}{}", name, end));
+    }
+
+    exit_on_broken_pipe(out.flush());
+}
+
+/// `--capture <name> [--capture-all]`
+pub fn capture(name: &str, scrollback: bool) {
+    if !tmux::session_exists(name) {
+        report::fail(format!("no session named {}", name), 1);
+    }
+
+    let contents = match tmux::capture_pane(name, scrollback) {
+        Ok(contents) => contents,
+        Err(error) => report::fail(format!("could not capture {}: {}", name, error), 1),
+    };
+
+    // tmux pads the visible screen out with blank lines
+    let contents = contents.trim_end();
+
+    if contents.is_empty() {
+        // said on stderr so a redirect to a file still gets an empty file
+        eprintln!("The active pane of {} is empty.", name);
+        return;
+    }
+
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+
+    exit_on_broken_pipe(writeln!(stdout, "{}", contents));
+    exit_on_broken_pipe(stdout.flush());
+}
+
+/// `--session-info <name>`
+pub fn session_info(name: &str, json: bool) {
+    match info::gather(name) {
+        Ok(Some(info)) if json => print_json(&info),
+        Ok(Some(info)) => print!("{}", info::render(&info)),
+        Ok(None) => {
+            report::fail(format!("no session named '{}'", name), 1);
+        }
+        Err(error) => {
+            report::fail(error, 1);
+        }
+    };
+}
+
+/// `--which`
+///
+/// Where the tmux that `tm` runs is and which version it is, exits with 127
+/// when it can't be found like a shell does
+pub fn which() {
+    let tmux_bin = &config::get().tmux_bin;
+
+    let path = match fs_utils::find_program(tmux_bin) {
+        Some(path) => path,
+        None => report::fail(format!("{} not found, is tmux installed?", tmux_bin), 127),
+    };
+
+    // tmux -V
+    match tmux::output(&["-V"]) {
+        Ok(version) => println!("{} ({})", path.display(), version.trim()),
+        Err(error) => {
+            report::fail(format!("could not run {}: {}", path.display(), error), 1);
+        }
+    }
+}
+
+/// `--init [--force] [--quiet]`
+pub fn init(force: bool, quiet: bool) {
+    let path = match config::config_path() {
+        Some(path) => path,
+        None => {
+            report::fail(
+                "neither $XDG_CONFIG_HOME nor $HOME is set, don't know where the config file goes",
+                1,
+            );
+        }
+    };
+
+    if path.exists() && !force {
+        report::fail(
+            format!(
+                "{} already exists, use --force to overwrite it",
+                path.display()
+            ),
+            1,
+        );
+    }
+
+    let written = paths::prepare(path.clone()).and_then(|path| fs::write(path, config::scaffold()));
+
+    match written {
+        Ok(()) if quiet => (),
+        Ok(()) => println!("Wrote {}", path.display()),
+        Err(error) => {
+            report::fail(format!("could not write {}: {}", path.display(), error), 1);
+        }
+    }
+}
+
+/// `--note <name> <text>` and `--clear-note <name>`
+pub fn note(name: &str, note: Option<&str>) {
+    if !tmux::session_exists(name) {
+        report::fail(format!("no session named {}", name), 1);
+    }
+
+    if let Err(error) = tmux::set_note(name, note) {
+        report::fail(format!("could not set the note for {}: {}", name, error), 1);
+    }
+}
+
+/// `tm attach <name>`, or `tm a <name>`
+///
+/// Exits with 1 when there's no such session or tmux couldn't attach
+pub fn attach(name: &str) {
+    if !tmux::session_exists(name) {
+        report::fail(format!("no session named {}", name), 1);
+    }
+
+    if !tmux::attach(name) {
+        process::exit(1);
+    }
+}
+
+/// `tm [--quiet] new <path> [--name <name>] [--no-attach]`
+///
+/// Asks nothing, so a script gets the same result every time: the name is
+/// the one suggested from the directory unless given, and a name that's
+/// already taken is an error rather than a reason to attach to that session
+pub fn new_session(path: &str, name: Option<&str>, attach: bool, quiet: bool) {
+    let path = match fs_utils::check_dir(path) {
+        Ok(path) => path,
+        Err(full_path) => {
+            report::fail(format!("{} is not a directory", full_path.display()), 1);
+        }
+    };
+
+    let name = match name {
+        Some(name) => name.to_string(),
+        None => session_utils::suggest_name_from_path(&path),
+    };
+
+    if let Err(reason) = session_utils::is_valid_session_name(&name) {
+        report::fail(
+            format!("'{}' can't be used: {}", name.escape_debug(), reason),
+            2,
+        );
+    }
+
+    if tmux::session_exists(&name) {
+        report::fail(format!("a session named {} already exists", name), 1);
+    }
+
+    match tmux::new_session(&name, &path) {
+        // the first session starts the server too, make sure it's really there before using it
+        Ok(()) if attach && !tmux::wait_for_session(&name) => {
+            report::fail(
+                format!(
+                    "session {} was created but tmux isn't listing it, not attaching",
+                    name
+                ),
+                1,
+            );
+        }
+        Ok(()) if attach => {
+            if !tmux::attach(&name) {
+                process::exit(1);
+            }
+        }
+        Ok(()) if quiet => (),
+        Ok(()) => println!("Created {}", name),
+        Err(NewSessionError::Duplicate) => {
+            report::fail(format!("a session named {} already exists", name), 1);
+        }
+        Err(NewSessionError::Failed(message)) => {
+            report::fail(format!("could not create session {}: {}", name, message), 1);
+        }
+        Err(NewSessionError::Io(error)) => tmux::fail_to_run(error),
+    }
+}
+
+/// `tm [--quiet] kill <name>`, or `tm kill --all` to stop the server once it has asked
+pub fn kill(name: Option<&str>, all: bool, quiet: bool) {
+    if all {
+        // with no server running there's nothing to kill
+        let count = tmux::running_sessions().sessions.len();
+
+        if count == 0 {
+            if !quiet {
+                println!("No existing tmux sessions found.");
+            }
+            return;
+        }
+
+        let question = format!("Kill the tmux server and all {} session(s)?", count);
+
+        if !prompt::confirm_destructive(&question) {
+            if !quiet {
+                println!("Nothing was killed.");
+            }
+            return;
+        }
+
+        match tmux::kill_server() {
+            Ok(()) if quiet => (),
+            Ok(()) => println!("Killed the tmux server"),
+            Err(error) => report::fail(format!("could not kill the tmux server: {}", error), 1),
+        }
+
+        return;
+    }
+
+    let name = match name {
+        Some(name) => name,
+        None => report::fail("give the name of a session to kill, or --all", 2),
+    };
+
+    if !tmux::session_exists(name) {
+        report::fail(format!("no session named {}", name), 1);
+    }
+
+    match tmux::kill_session(&tmux::exact(name)) {
+        Ok(()) if quiet => (),
+        Ok(()) => println!("Killed {}", name),
+        Err(error) => report::fail(format!("could not kill {}: {}", name, error), 1),
+    }
+}
+
+/// `--rename-interactive <name>`
+pub fn rename_interactive(name: &str) {
+    // with no server running there's nothing to rename
+    let sessions = tmux::running_sessions();
+    let existing = sessions.names();
+
+    if !existing.contains(&name) {
+        report::fail(format!("no session named {}", name), 1);
+    }
+
+    let new_name = match session_utils::rename_session_name(name, &existing) {
+        Some(new_name) => new_name,
+        None => return,
+    };
+
+    match tmux::rename_session(name, &new_name) {
+        Ok(()) => println!("Renamed {} to {}", name, new_name),
+        Err(error) => {
+            report::fail(format!("could not rename {}: {}", name, error), 1);
+        }
+    }
+}
+
+/// `--rename-from-dir <name>`
+///
+/// Suggests a name from the session's directory the way new sessions are
+/// named and asks before renaming to it. When that name is taken or can't be
+/// used another is asked for instead.
+pub fn rename_from_dir(name: &str) {
+    // with no server running there's nothing to rename
+    let sessions = tmux::running_sessions();
+    let existing = sessions.names();
+
+    if !existing.contains(&name) {
+        report::fail(format!("no session named {}", name), 1);
+    }
+
+    let path = match tmux::session_path(name) {
+        Ok(path) => path,
+        Err(error) => {
+            report::fail(
+                format!("could not get the directory for {}: {}", name, error),
+                1,
+            );
+        }
+    };
+
+    let suggested = session_utils::suggest_name_from_path(Path::new(&path));
+
+    if suggested == name {
+        println!("{} already matches its directory {}", name, path);
+        return;
+    }
+
+    let new_name = if existing.contains(&suggested.as_str()) {
+        prompt::say(&format!("A session named '{}' already exists.", suggested));
+        session_utils::rename_session_name(name, &existing)
+    } else if let Err(reason) = session_utils::is_valid_session_name(&suggested) {
+        prompt::say(&format!(
+            "'{}' can't be used: {}",
+            suggested.escape_debug(),
+            reason
+        ));
+        session_utils::rename_session_name(name, &existing)
+    } else {
+        let question = format!("Rename {} to {}?", name, suggested);
+
+        match prompt::confirm(&question, config::get().confirm_default) {
+            Some(true) => Some(suggested),
+            Some(false) | None => None,
+        }
+    };
+
+    let new_name = match new_name {
+        Some(new_name) => new_name,
+        None => return,
+    };
+
+    match tmux::rename_session(name, &new_name) {
+        Ok(()) => println!("Renamed {} to {}", name, new_name),
+        Err(error) => {
+            report::fail(format!("could not rename {}: {}", name, error), 1);
+        }
+    }
+}
+
+/// `--send-keys <name> <keys>...`
+pub fn send_keys(name: &str, keys: &[&str]) {
+    if !tmux::session_exists(name) {
+        report::fail(format!("no session named {}", name), 1);
+    }
+
+    if let Err(error) = tmux::send_keys(name, keys) {
+        report::fail(format!("could not send keys to {}: {}", name, error), 1);
+    }
+}
+
+/// `--set-option <name> <option> <value>`
+///
+/// Handed to tmux as is, so whatever tmux makes of the option and value goes
+pub fn set_option(name: &str, option: &str, value: &str) {
+    if !tmux::session_exists(name) {
+        report::fail(format!("no session named {}", name), 1);
+    }
+
+    match tmux::set_option(name, option, value) {
+        Ok(()) => println!("Set {} to {} for {}", option, value, name),
+        Err(error) => {
+            report::fail(
+                format!("could not set {} for {}: {}", option, name, error),
+                1,
+            );
+        }
+    }
+}
+
+/// `--run <name> <command> [--new-window]`
+///
+/// Types `command` into the session's active pane and presses Enter, in a
+/// new window opened in the session's directory first with `new_window`
+pub fn run(name: &str, command: &str, new_window: bool) {
+    if !tmux::session_exists(name) {
+        report::fail(format!("no session named {}", name), 1);
+    }
+
+    if new_window {
+        let path = match tmux::session_path(name) {
+            Ok(path) => path,
+            Err(error) => {
+                report::fail(
+                    format!("could not get the directory for {}: {}", name, error),
+                    1,
+                );
+            }
+        };
+
+        // the new window becomes the current one, so that's where the keys go
+        if let Err(error) = tmux::new_window(&tmux::exact(name), Path::new(&path), None) {
+            report::fail(format!("could not open a window in {}: {}", name, error), 1);
+        }
+    }
+
+    if let Err(error) = tmux::send_keys(name, &[command, "Enter"]) {
+        report::fail(
+            format!("could not run {} in {}: {}", command, name, error),
+            1,
+        );
+    }
+}
+
+/// `--select-window-by-name <session> <window>`
+pub fn select_window_by_name(session: &str, window: &str) {
+    if !tmux::session_exists(session) {
+        report::fail(format!("no session named {}", session), 1);
+    }
+
+    let target = tmux::exact(session);
+    let windows = match tmux::list_windows(Some(&target)) {
+        Ok(windows) => windows,
+        Err(error) => report::fail(
+            format!("could not list the windows of {}: {}", session, error),
+            1,
+        ),
+    };
+
+    let matching: Vec<usize> = windows
+        .iter()
+        .filter(|candidate| candidate.name == window)
+        .map(|candidate| candidate.index)
+        .collect();
+
+    let index = match matching.as_slice() {
+        [] => report::fail(format!("{} has no window named {}", session, window), 1),
+        [index] => *index,
+        [index, ..] => {
+            report::error(format!(
+                "{} windows in {} are named {}, using the first at index {}",
+                matching.len(),
+                session,
+                window,
+                index
+            ));
+            *index
+        }
+    };
+
+    if let Err(error) = tmux::select_window(&format!("{}:{}", target, index)) {
+        report::fail(
+            format!("could not select {} in {}: {}", window, session, error),
+            1,
+        );
+    }
+
+    if !tmux::attach(session) {
+        process::exit(1);
+    }
+}
+
+/// `--new-window [<session>]`
+///
+/// Without a session inside tmux the window goes into the current one
+pub fn new_window(session: Option<&str>) {
+    let session = match session.map(String::from).or_else(tmux::current_session) {
+        Some(session) => session,
+        None => report::fail("--new-window needs a session when not run inside tmux", 2),
+    };
+
+    if !tmux::session_exists(&session) {
+        report::fail(format!("no session named {}", session), 1);
+    }
+
+    let path = match fs_utils::prompt_valid_path() {
+        Some(path) => path,
+        None => return,
+    };
+
+    let name = match prompt::ask("Window name (blank for the default): ") {
+        Some(name) => name,
+        None => return,
+    };
+    let name = Some(name.as_str()).filter(|name| !name.is_empty());
+
+    if let Err(error) = tmux::new_window(&session, &path, name) {
+        report::fail(
+            format!("could not add a window to {}: {}", session, error),
+            1,
+        );
+    }
+}
+
+/// `--rename-session-interactive`
+///
+/// Every new name is asked for first and nothing is renamed until the whole
+/// set has been checked
+pub fn rename_sessions() {
+    // with no server running there's nothing to rename
+    let sessions = tmux::running_sessions();
+    let existing = sessions.names();
+
+    if existing.is_empty() {
+        println!("No existing tmux sessions found.");
+        return;
+    }
+
+    println!("Enter a new name for each session, or nothing to keep it.");
+
+    let mut renames = Vec::new();
+
+    for name in &existing {
+        loop {
+            let answer = match prompt::ask(&format!("{} -> ", name)) {
+                Some(answer) => answer,
+                None => return,
+            };
+
+            if answer.is_empty() {
+                break;
+            }
+
+            match session_utils::is_valid_session_name(&answer) {
+                Ok(()) => {
+                    renames.push((name.to_string(), answer));
+                    break;
+                }
+                Err(reason) => println!("'{}' can't be used: {}", answer.escape_debug(), reason),
+            }
+        }
+    }
+
+    let planned = match session_utils::plan_renames(&renames, &existing) {
+        Ok(planned) => planned,
+        Err(reason) => report::fail(format!("nothing was renamed, {}", reason), 1),
+    };
+
+    if planned.is_empty() {
+        println!("Nothing to rename.");
+        return;
+    }
+
+    planned
+        .iter()
+        .for_each(|(from, to)| println!("  {} -> {}", from, to));
+
+    if prompt::confirm("Rename them?", config::get().confirm_default) != Some(true) {
+        return;
+    }
+
+    for (from, to) in &planned {
+        if let Err(error) = tmux::rename_session(from, to) {
+            report::fail(format!("could not rename {} to {}: {}", from, to, error), 1);
+        }
+    }
+}
+
+/// `--spawn <name>`
+///
+/// Blocks until the terminal is closed
+pub fn spawn(name: &str) {
+    if !tmux::session_exists(name) {
+        report::fail(format!("no session named {}", name), 1);
+    }
+
+    let config = config::get();
+    let terminal = if config.terminal.trim().is_empty() {
+        env::var("TERMINAL").unwrap_or_default()
+    } else {
+        config.terminal.clone()
+    };
+
+    // the terminal may carry its own arguments, e.g. `alacritty --class tm`
+    let mut words = terminal.split_whitespace().map(String::from);
+    let command = match words.next() {
+        Some(command) => command,
+        None => {
+            report::fail(
+                "$TERMINAL is not set and no terminal is configured, don't know what to open",
+                1,
+            );
+        }
+    };
+
+    let tmux_attach = tmux::attach_app(name, tmux::grab_others(name));
+    let mut args: Vec<String> = words.collect();
+
+    args.extend(config.terminal_exec.split_whitespace().map(String::from));
+    args.push(tmux_attach.command);
+    args.extend(tmux_attach.args);
+
+    match scuttle::run_status(&scuttle::App { command, args }) {
+        Ok(status) if status.success() => (),
+        Ok(status) => {
+            report::fail(format!("the terminal exited with {}", status), 1);
+        }
+        Err(error) => {
+            report::fail(format!("could not open {}: {}", terminal, error), 1);
+        }
+    }
+}
+
+/// `--templates-list`
+pub fn templates_list(json: bool) {
+    let templates = &config::get().templates;
+
+    if json {
+        return print_json(templates);
+    }
+
+    if templates.is_empty() {
+        println!("No templates configured, add a [templates.<name>] table to the config file");
+        return;
+    }
+
+    for (name, template) in templates {
+        println!("{}", name);
+
+        for (index, window) in template.windows.iter().enumerate() {
+            if window.actions.is_empty() {
+                println!("  {}) {}", index + 1, window.name);
+            } else {
+                let actions = window.actions.join("; ");
+
+                println!("  {}) {}: {}", index + 1, window.name, actions);
+            }
+        }
+    }
+}
+
+/// `--export-bookmarks <file>`
+pub fn export_bookmarks(file: &Path) {
+    let saved = match bookmarks::load() {
+        Ok(saved) => saved,
+        Err(error) => report::fail(format!("could not read the bookmarks: {}", error), 1),
+    };
+
+    match bookmarks::write(file, &saved) {
+        Ok(()) => println!(
+            "Exported {} bookmarks to {}",
+            saved.bookmarks.len(),
+            file.display()
+        ),
+        Err(error) => report::fail(format!("could not export the bookmarks: {}", error), 1),
+    }
+}
+
+/// `--import-bookmarks <file>`
+///
+/// Adds the bookmarks that aren't here yet and says which ones clash with a
+/// bookmark of the same name, those are left as they were
+pub fn import_bookmarks(file: &Path) {
+    let incoming = match bookmarks::read(file) {
+        Ok(incoming) => incoming,
+        Err(error) => report::fail(format!("could not import the bookmarks: {}", error), 1),
+    };
+
+    let mut saved = match bookmarks::load() {
+        Ok(saved) => saved,
+        Err(error) => report::fail(format!("could not read the bookmarks: {}", error), 1),
+    };
+
+    let imported = bookmarks::merge(&mut saved, incoming);
+    let mut added = 0;
+
+    for result in &imported {
+        match result {
+            Imported::Added(name, path) => {
+                added += 1;
+
+                if Path::new(path).is_dir() {
+                    println!("Added {} ({})", name, path);
+                } else {
+                    println!("Added {} ({}, which isn't a directory here)", name, path);
+                }
+            }
+            Imported::Unchanged(_name) => (),
+            Imported::Conflict {
+                name,
+                kept,
+                offered,
+            } => println!("Kept {} ({}), the file has it as {}", name, kept, offered),
+            Imported::Invalid(name, reason) => println!("Skipped {}: {}", name, reason),
+        }
+    }
+
+    if added == 0 {
+        println!("No new bookmarks in {}", file.display());
+        return;
+    }
+
+    if let Err(error) = bookmarks::store(&saved) {
+        report::fail(format!("could not save the bookmarks: {}", error), 1);
+    }
+}
+
+/// `--save-layout <file> [--quiet]`
+pub fn save_layout(file: &Path, quiet: bool) {
+    match layout::save(file) {
+        Ok(_saved) if quiet => (),
+        Ok(saved) => println!(
+            "Saved {} sessions to {}",
+            saved.sessions.len(),
+            file.display()
+        ),
+        Err(error) => {
+            report::fail(format!("could not save layout: {}", error), 1);
+        }
+    };
+}
+
+/// `--clone-layout <source> <name> [--no-attach]`
+///
+/// Starts `name` with the windows and directories of `source`, then attaches
+/// to it unless `attach` is off
+pub fn clone_layout(source: &str, name: &str, attach: bool) {
+    if let Err(reason) = session_utils::is_valid_session_name(name) {
+        report::fail(
+            format!("'{}' can't be used: {}", name.escape_debug(), reason),
+            2,
+        );
+    }
+
+    // with no server running there's nothing to clone
+    let sessions = tmux::running_sessions();
+    let existing = sessions.names();
+
+    if !existing.contains(&source) {
+        report::fail(format!("no session named {}", source), 1);
+    }
+
+    if existing.contains(&name) {
+        report::fail(format!("a session named {} already exists", name), 1);
+    }
+
+    match layout::clone(source, name) {
+        // what was running in the windows isn't copied, tmux doesn't reliably say what it was
+        Ok(windows) => println!(
+            "Created {} ({} windows) like {}, without what was started in them",
+            name, windows, source
+        ),
+        Err(error) => {
+            report::fail(format!("could not clone {}: {}", source, error), 1);
+        }
+    }
+
+    if attach {
+        tmux::attach(name);
+    }
+}
+
+/// `--restore-layout <file> [--dry-run] [--no-preview] [--quiet]`
+///
+/// What would change is shown first unless `preview` is off, and with
+/// `dry_run` that's all that happens. `quiet` leaves out the line for each
+/// session restored or skipped.
+pub fn restore_layout(file: &Path, dry_run: bool, preview: bool, quiet: bool) {
+    let saved = match layout::load(file) {
+        Ok(saved) => saved,
+        Err(error) => {
+            report::fail(format!("could not restore layout: {}", error), 1);
+        }
+    };
+
+    if preview {
+        match layout::preview(&saved) {
+            Ok(changes) => print_changes(&changes),
+            Err(error) => {
+                report::fail(format!("could not compare the layout: {}", error), 1);
+            }
+        }
+    }
+
+    if dry_run {
+        return;
+    }
+
+    let restored = match layout::restore(&saved) {
+        Ok(restored) => restored,
+        Err(error) => {
+            report::fail(format!("could not restore layout: {}", error), 1);
+        }
+    };
+
+    if quiet {
+        return;
+    }
+
+    for session in restored {
+        match session {
+            Restored::Created(name, windows) => println!("Created {} ({} windows)", name, windows),
+            Restored::Skipped(name) => println!("Skipped {} (already running)", name),
+        }
+    }
+}
+
+/// Show what restoring a layout would do, diff style
+fn print_changes(changes: &[Change]) {
+    for change in changes {
+        match change {
+            Change::Create(name, windows) => {
+                let line = format!("+ {} ({} windows)", name, windows);
+
+                println!("{}", term::paint(&line, Color::Green));
+            }
+            Change::Unchanged(name) => {
+                let line = format!("= {} (running, skipped)", name);
+
+                println!("{}", term::paint(&line, Color::Dim));
+            }
+            Change::Differs {
+                name,
+                missing,
+                extra,
+            } => {
+                let line = format!("~ {} (running but different, skipped)", name);
+
+                println!("{}", term::paint(&line, Color::Yellow));
+                missing
+                    .iter()
+                    .for_each(|window| println!("    saved but not running: {}", window));
+                extra
+                    .iter()
+                    .for_each(|window| println!("    running but not saved: {}", window));
+            }
+        }
+    }
+}
+
+/// `--detach <name> [--then-attach] [--quiet]`
+///
+/// Kicks every client off the session, which may be somebody else's terminal,
+/// so they're listed first and it asks. With none attached there's nothing to ask.
+pub fn detach(name: &str, then_attach: bool, quiet: bool) {
+    if !tmux::session_exists(name) {
+        report::fail(format!("no session named '{}'", name), 1);
+    }
+
+    let clients = match tmux::list_clients(name) {
+        Ok(clients) => clients,
+        Err(error) => {
+            report::fail(
+                format!("could not list the clients of {}: {}", name, error),
+                1,
+            );
+        }
+    };
+
+    if clients.is_empty() {
+        if !quiet {
+            println!("No clients are attached to {}", name);
+        }
+    } else if !tmux::confirm_detach(name, &clients) {
+        println!("Nothing was detached.");
+        return;
+    } else {
+        match tmux::detach_clients(name) {
+            Ok(()) if quiet => (),
+            Ok(()) => println!("Detached all clients from {}", name),
+            Err(error) => {
+                report::fail(
+                    format!("could not detach clients from {}: {}", name, error),
+                    1,
+                );
+            }
+        };
+    }
+
+    if then_attach {
+        tmux::attach(name);
+    }
+}
+
+/// `--tree`
+pub fn tree() {
+    let nodes = match tree::gather() {
+        Ok(nodes) => nodes,
+        Err(error) => {
+            report::fail(format!("could not list the sessions: {}", error), 1);
+        }
+    };
+
+    if nodes.is_empty() {
+        println!("No existing tmux sessions found.");
+        return;
+    }
+
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+
+    exit_on_broken_pipe(write!(stdout, "{}", tree::render(&nodes)));
+    exit_on_broken_pipe(stdout.flush());
+}
+
+/// `--merge <source> <destination>`
+///
+/// Moves every window of `source` into `destination` after asking, which
+/// leaves `source` without windows so it's gone afterwards
+pub fn merge(source: &str, destination: &str) {
+    if source == destination {
+        report::fail(format!("can't merge {} into itself", source), 2);
+    }
+
+    for name in [source, destination] {
+        if !tmux::session_exists(name) {
+            report::fail(format!("no session named {}", name), 1);
+        }
+    }
+
+    let windows = match tmux::list_windows(Some(source)) {
+        Ok(windows) => windows,
+        Err(error) => {
+            report::fail(
+                format!("could not list the windows of {}: {}", source, error),
+                1,
+            );
+        }
+    };
+
+    println!("This will move into {}:", destination);
+    windows
+        .iter()
+        .for_each(|window| println!("  {}: {}", window.index, window.name));
+    println!("and {} will be gone once they have.", source);
+
+    if !prompt::confirm_destructive(&format!("Merge {} into {}?", source, destination)) {
+        println!("Nothing was moved.");
+        return;
+    }
+
+    // no index, so tmux gives each one the next free index instead of clashing
+    let target = format!("{}:", tmux::exact(destination));
+
+    for window in &windows {
+        let moving = format!("{}:{}", tmux::exact(source), window.index);
+
+        if let Err(error) = tmux::move_window(&moving, &target) {
+            report::fail(
+                format!(
+                    "could not move {}:{} ({}) into {}: {}",
+                    source, window.index, window.name, destination, error
+                ),
+                1,
+            );
+        }
+    }
+
+    // tmux ends a session once its last window is gone, unless it was given another meanwhile
+    if tmux::session_exists(source) {
+        if let Err(error) = tmux::kill_session(&tmux::exact(source)) {
+            report::error(format!("could not kill {}: {}", source, error));
+        }
+    }
+
+    match tmux::list_windows(Some(destination)) {
+        Ok(windows) => println!(
+            "Merged {} into {}, which now has {} windows",
+            source,
+            destination,
+            windows.len()
+        ),
+        Err(error) => report::error(format!(
+            "merged {} into {} but could not list its windows: {}",
+            source, destination, error
+        )),
+    }
+}
+
+/// `--link-window`
+///
+/// Walks through picking a window and the session it should also appear in
+pub fn link_window() {
+    // with no server running there is nothing to link
+    let sessions = tmux::running_sessions();
+    let names = sessions.names();
+
+    if names.len() < 2 {
+        println!("Linking a window needs at least two sessions.");
+        return;
+    }
+
+    let source = match prompt::pick("Link a window from which session?", &names) {
+        Some(source) => names[source],
+        None => return,
+    };
+
+    let windows = match tmux::list_windows(Some(source)) {
+        Ok(windows) => windows,
+        Err(error) => {
+            report::fail(
+                format!("could not list the windows of {}: {}", source, error),
+                1,
+            );
+        }
+    };
+
+    let labels: Vec<String> = windows
+        .iter()
+        .map(|window| format!("{}: {}", window.index, window.name))
+        .collect();
+
+    let window = match prompt::pick("Which window?", &labels) {
+        Some(window) => &windows[window],
+        None => return,
+    };
+
+    let others: Vec<&str> = names
+        .iter()
+        .copied()
+        .filter(|name| *name != source)
+        .collect();
+
+    let destination = match prompt::pick("Link it into which session?", &others) {
+        Some(destination) => others[destination],
+        None => return,
+    };
+
+    let index = match prompt::ask("Window index in the new session (blank for the next free one): ")
+    {
+        Some(index) => index,
+        None => return,
+    };
+
+    let source = format!("{}:{}", window.session, window.index);
+    let target = format!("{}:{}", destination, index);
+
+    let question = format!("Link {} ({}) into {}?", source, window.name, target);
+
+    if prompt::confirm(&question, config::get().confirm_default) != Some(true) {
+        return;
+    }
+
+    let result = match tmux::link_window(&source, &target, false) {
+        Err(LinkWindowError::IndexInUse) => {
+            println!("{} already has a window at index {}.", destination, index);
+
+            if !prompt::confirm_destructive("Replace it?") {
+                return;
+            }
+
+            tmux::link_window(&source, &target, true)
+        }
+        result => result,
+    };
+
+    match result {
+        Ok(()) => println!("Linked {} into {}", source, destination),
+        Err(LinkWindowError::IndexInUse) => {
+            report::fail(
+                format!("{} still has a window at index {}", destination, index),
+                1,
+            );
+        }
+        Err(LinkWindowError::Failed(message)) => {
+            report::fail(format!("could not link {}: {}", source, message), 1);
+        }
+        Err(LinkWindowError::Io(error)) => tmux::fail_to_run(error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn listed(print0: bool) -> Vec<u8> {
+        let sessions = SessionList::parse("work\nplay\n");
+        let mut out = Vec::new();
+
+        list_names(&mut out, &sessions, print0);
+        out
+    }
+
+    #[test]
+    fn list_names_ends_each_name_with_a_nul_for_print0() {
+        assert_eq!(listed(true), b"work\0play\0");
+    }
+
+    #[test]
+    fn list_names_puts_one_name_on_each_line() {
+        assert_eq!(listed(false), b"work\nplay\n");
+    }
+}
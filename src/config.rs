@@ -0,0 +1,672 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+
+use crate::paths;
+
+// the configuration the rest of the program reads from once `main` has resolved it
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// Settings that change how `tm` behaves
+///
+/// Values are resolved in layers, each one overriding the last: built-in
+/// defaults, then the config file, then environment variables.
+#[derive(Clone, Debug, Serialize)]
+pub struct Config {
+    /// the tmux binary to run (`TM_TMUX_BIN`)
+    pub tmux_bin: String,
+    /// seconds to wait at a destructive confirmation before giving up, 0 waits
+    /// forever (`TM_CONFIRM_TIMEOUT`)
+    pub confirm_timeout: u64,
+    /// what an empty answer to a yes/no question means, destructive ones are
+    /// always no (`TM_CONFIRM_DEFAULT`)
+    pub confirm_default: ConfirmDefault,
+    /// what pressing Enter at the menu without a number picks (`TM_DEFAULT_SELECTION`)
+    pub default_selection: DefaultSelection,
+    /// longest session name to allow before offering to shorten it, 0 for no
+    /// limit (`TM_MAX_NAME_LENGTH`)
+    pub max_name_length: usize,
+    /// how names are suggested for new sessions, `{dir}`, `{parent}` and
+    /// `{git_root}` are replaced by those directories' names (`TM_NAME_TEMPLATE`)
+    pub name_template: String,
+    /// name new sessions after the project in a Cargo.toml, package.json,
+    /// pyproject.toml or README.md in their directory instead of `name_template`
+    /// (`TM_NAME_FROM_MANIFEST`)
+    pub name_from_manifest: bool,
+    /// regular expression every new or renamed session's name has to match,
+    /// empty to allow any name tmux does (`TM_NAME_POLICY`)
+    pub name_policy: String,
+    /// give new sessions the values `copy_env_vars` have here (`TM_COPY_ENV`)
+    pub copy_env: bool,
+    /// environment variables `copy_env` copies, only read from the config file
+    pub copy_env_vars: Vec<String>,
+    /// detach every other client when attaching so the session takes this
+    /// terminal's size (`TM_GRAB`)
+    pub grab: bool,
+    /// show the command that will create a new session and ask before
+    /// running it (`TM_PREVIEW`)
+    pub preview: bool,
+    /// shell the first window of a new session runs, empty for tmux's own
+    /// `default-command` (`TM_SHELL`)
+    pub shell: String,
+    /// terminal emulator `--spawn` opens, empty to use `$TERMINAL` (`TM_TERMINAL`)
+    pub terminal: String,
+    /// what goes between the terminal and the command it should run, `-e` for
+    /// most, `--` for gnome-terminal, empty for kitty (`TM_TERMINAL_EXEC`)
+    pub terminal_exec: String,
+    /// seconds between refreshes in `--watch` (`TM_WATCH_INTERVAL`)
+    pub watch_interval: u64,
+    /// don't show the tip for new users on the first run (`TM_NO_TIPS`)
+    pub no_tips: bool,
+    /// keep the last failed tmux command for `--dump-last-error` (`TM_RECORD_ERRORS`)
+    pub record_errors: bool,
+    /// named window setups for new sessions, only read from the config file
+    pub templates: BTreeMap<String, Template>,
+    /// words that stand for flags when given as the first argument, e.g.
+    /// `w = "--watch --active-only"`, only read from the config file
+    pub aliases: BTreeMap<String, String>,
+}
+
+/// A set of windows to start a session with
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Template {
+    pub windows: Vec<TemplateWindow>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TemplateWindow {
+    pub name: String,
+    /// commands typed into the window once it's open
+    #[serde(default)]
+    pub actions: Vec<String>,
+}
+
+/// The session an empty answer at the menu attaches to
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DefaultSelection {
+    /// nothing, an empty answer isn't a choice
+    None,
+    /// the first session listed
+    First,
+    /// the most recently attached session
+    Mru,
+}
+
+impl FromStr for DefaultSelection {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "none" => Ok(DefaultSelection::None),
+            "first" => Ok(DefaultSelection::First),
+            "mru" => Ok(DefaultSelection::Mru),
+            _ => Err(format!("unknown default selection '{}'", value)),
+        }
+    }
+}
+
+/// The answer Enter gives to a yes/no question
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConfirmDefault {
+    Yes,
+    No,
+}
+
+impl FromStr for ConfirmDefault {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "yes" => Ok(ConfirmDefault::Yes),
+            "no" => Ok(ConfirmDefault::No),
+            _ => Err(format!("unknown confirm default '{}'", value)),
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            tmux_bin: String::from("tmux"),
+            confirm_timeout: 0,
+            confirm_default: ConfirmDefault::No,
+            default_selection: DefaultSelection::None,
+            max_name_length: 0,
+            name_template: String::from("{dir}"),
+            name_from_manifest: false,
+            name_policy: String::new(),
+            copy_env: false,
+            copy_env_vars: vec![
+                String::from("PATH"),
+                String::from("LANG"),
+                String::from("SSH_AUTH_SOCK"),
+            ],
+            grab: false,
+            preview: false,
+            shell: String::new(),
+            terminal: String::new(),
+            terminal_exec: String::from("-e"),
+            watch_interval: 2,
+            no_tips: false,
+            record_errors: false,
+            templates: BTreeMap::new(),
+            aliases: BTreeMap::new(),
+        }
+    }
+}
+
+/// The config file as written, every key is optional
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct FileConfig {
+    tmux_bin: Option<String>,
+    confirm_timeout: Option<u64>,
+    confirm_default: Option<ConfirmDefault>,
+    default_selection: Option<DefaultSelection>,
+    max_name_length: Option<usize>,
+    name_template: Option<String>,
+    name_from_manifest: Option<bool>,
+    name_policy: Option<String>,
+    copy_env: Option<bool>,
+    copy_env_vars: Option<Vec<String>>,
+    grab: Option<bool>,
+    preview: Option<bool>,
+    shell: Option<String>,
+    terminal: Option<String>,
+    terminal_exec: Option<String>,
+    watch_interval: Option<u64>,
+    no_tips: Option<bool>,
+    record_errors: Option<bool>,
+    templates: Option<BTreeMap<String, Template>>,
+    aliases: Option<BTreeMap<String, String>>,
+}
+
+/// The fully resolved configuration along with where each value came from
+#[derive(Debug, Serialize)]
+pub struct Resolved {
+    /// the config file that was read, if there was one
+    pub file: Option<PathBuf>,
+    pub config: Config,
+    /// the layer each value was taken from, keyed by config name
+    pub sources: BTreeMap<&'static str, String>,
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Read(PathBuf, io::Error),
+    Parse(PathBuf, toml::de::Error),
+    Env(&'static str, String),
+    NamePolicy(String, regex::Error),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Read(path, error) => {
+                write!(f, "could not read {}: {}", path.display(), error)
+            }
+            ConfigError::Parse(path, error) => {
+                write!(f, "invalid config in {}: {}", path.display(), error)
+            }
+            ConfigError::Env(var, value) => write!(f, "invalid value for {}: {}", var, value),
+            ConfigError::NamePolicy(policy, error) => {
+                write!(f, "invalid name_policy '{}': {}", policy, error)
+            }
+        }
+    }
+}
+
+/// Where the config file lives
+///
+/// `config.toml` in the config directory, usually `~/.config/tm/config.toml`
+pub fn config_path() -> Option<PathBuf> {
+    paths::config_dir().map(|directory| directory.join("config.toml"))
+}
+
+/// A config file with every key commented out at its default value
+///
+/// The values come from `Config::default` so they can't drift from what `tm`
+/// actually does, only the explanations are written out here.
+pub fn scaffold() -> String {
+    let defaults = Config::default();
+    let keys = [
+        (
+            "the tmux binary to run",
+            "tmux_bin",
+            value(&defaults.tmux_bin),
+            "TM_TMUX_BIN",
+        ),
+        (
+            "seconds to wait at a destructive confirmation before giving up, 0 waits forever",
+            "confirm_timeout",
+            value(&defaults.confirm_timeout),
+            "TM_CONFIRM_TIMEOUT",
+        ),
+        (
+            "what Enter means at a yes/no question: \"yes\" or \"no\", destructive ones are always no",
+            "confirm_default",
+            value(&defaults.confirm_default),
+            "TM_CONFIRM_DEFAULT",
+        ),
+        (
+            "what pressing Enter at the menu picks: \"none\", \"first\" or \"mru\"",
+            "default_selection",
+            value(&defaults.default_selection),
+            "TM_DEFAULT_SELECTION",
+        ),
+        (
+            "longest session name to allow before offering to shorten it, 0 for no limit",
+            "max_name_length",
+            value(&defaults.max_name_length),
+            "TM_MAX_NAME_LENGTH",
+        ),
+        (
+            "how new sessions are named, from {dir}, {parent} and {git_root}",
+            "name_template",
+            value(&defaults.name_template),
+            "TM_NAME_TEMPLATE",
+        ),
+        (
+            "name new sessions after the project in a Cargo.toml, package.json, pyproject.toml or README.md in their directory",
+            "name_from_manifest",
+            value(&defaults.name_from_manifest),
+            "TM_NAME_FROM_MANIFEST",
+        ),
+        (
+            "regular expression new and renamed session names must match, e.g. \"^[a-z0-9-]+$\", empty for any",
+            "name_policy",
+            value(&defaults.name_policy),
+            "TM_NAME_POLICY",
+        ),
+        (
+            "give new sessions the values copy_env_vars have where tm is run",
+            "copy_env",
+            value(&defaults.copy_env),
+            "TM_COPY_ENV",
+        ),
+        (
+            "detach other clients when attaching so the session fits this terminal",
+            "grab",
+            value(&defaults.grab),
+            "TM_GRAB",
+        ),
+        (
+            "show the command that will create a new session and ask before running it",
+            "preview",
+            value(&defaults.preview),
+            "TM_PREVIEW",
+        ),
+        (
+            "shell the first window of a new session runs, empty for tmux's default-command",
+            "shell",
+            value(&defaults.shell),
+            "TM_SHELL",
+        ),
+        (
+            "terminal emulator --spawn opens, empty to use $TERMINAL",
+            "terminal",
+            value(&defaults.terminal),
+            "TM_TERMINAL",
+        ),
+        (
+            "what goes before the command the terminal runs: \"-e\" for most, \"--\" for gnome-terminal, empty for kitty",
+            "terminal_exec",
+            value(&defaults.terminal_exec),
+            "TM_TERMINAL_EXEC",
+        ),
+        (
+            "seconds between refreshes in --watch",
+            "watch_interval",
+            value(&defaults.watch_interval),
+            "TM_WATCH_INTERVAL",
+        ),
+        (
+            "don't show the tip for new users on the first run",
+            "no_tips",
+            value(&defaults.no_tips),
+            "TM_NO_TIPS",
+        ),
+        (
+            "keep the last failed tmux command for --dump-last-error",
+            "record_errors",
+            value(&defaults.record_errors),
+            "TM_RECORD_ERRORS",
+        ),
+    ];
+
+    let mut contents = String::from(
+        "# tm configuration\n\
+         #\n\
+         # Every key is shown commented out at its default, uncomment a line to change it.\n\
+         # The environment variable after each explanation overrides what's set here.\n",
+    );
+
+    for (explanation, key, default, env) in keys {
+        contents.push_str(&format!(
+            "\n# {} ({})\n# {} = {}\n",
+            explanation, env, key, default
+        ));
+    }
+
+    contents.push_str(&format!(
+        "\n# the environment variables copy_env copies, only set here\n# copy_env_vars = {}\n",
+        value(&defaults.copy_env_vars)
+    ));
+    contents.push_str(
+        "\n# named window setups for new sessions, e.g.\n\
+         # [templates.dev]\n\
+         # windows = [{ name = \"editor\", actions = [\"vim\"] }, { name = \"shell\" }]\n",
+    );
+    contents.push_str(
+        "\n# words that stand for flags as the first argument, e.g. tm w for tm --watch --active-only\n\
+         # [aliases]\n\
+         # w = \"--watch --active-only\"\n",
+    );
+
+    contents
+}
+
+/// A default written out as a TOML value
+fn value<T: Serialize>(default: &T) -> String {
+    // every key is a plain string, number or bool and those are written the same in JSON
+    serde_json::to_string(default).unwrap_or_default()
+}
+
+/// Resolve one config value that can only be set in the config file
+///
+/// `slot` holds the default which the value from the file overrides
+fn resolve_from_file<T>(
+    slot: &mut T,
+    sources: &mut BTreeMap<&'static str, String>,
+    key: &'static str,
+    file: (Option<T>, &str),
+) {
+    sources.insert(key, String::from("default"));
+
+    if let Some(value) = file.0 {
+        *slot = value;
+        sources.insert(key, file.1.to_string());
+    }
+}
+
+/// Resolve one config value, remembering which layer it came from
+///
+/// `slot` holds the default, then the value from the file overrides it and
+/// finally the environment variable `env`.
+fn resolve<T: FromStr>(
+    slot: &mut T,
+    sources: &mut BTreeMap<&'static str, String>,
+    key: &'static str,
+    file: (Option<T>, &str),
+    env: &'static str,
+) -> Result<(), ConfigError> {
+    resolve_from_file(slot, sources, key, file);
+
+    if let Some(value) = env_value(env)? {
+        *slot = value;
+        sources.insert(key, format!("env ({})", env));
+    }
+
+    Ok(())
+}
+
+/// Read an environment variable, `None` if it isn't set
+fn env_value<T: FromStr>(var: &'static str) -> Result<Option<T>, ConfigError> {
+    match env::var(var) {
+        Ok(value) => match parse_env(&value) {
+            Some(parsed) => Ok(Some(parsed)),
+            None => Err(ConfigError::Env(var, value)),
+        },
+        Err(_error) => Ok(None),
+    }
+}
+
+/// Parse the value of an environment variable
+///
+/// Switches are usually set with `1`, `yes` or `TRUE` rather than Rust's
+/// `true`, so any of 1/0, yes/no and true/false in any case is taken for a
+/// bool when the value doesn't parse as it is.
+fn parse_env<T: FromStr>(value: &str) -> Option<T> {
+    if let Ok(parsed) = value.parse::<T>() {
+        return Some(parsed);
+    }
+
+    let word = match value.to_ascii_lowercase().as_str() {
+        "1" | "yes" | "true" => "true",
+        "0" | "no" | "false" => "false",
+        _ => return None,
+    };
+
+    word.parse::<T>().ok()
+}
+
+/// Resolve the configuration from defaults, the config file and the environment
+pub fn load() -> Result<Resolved, ConfigError> {
+    let mut resolved = Resolved {
+        file: None,
+        config: Config::default(),
+        sources: BTreeMap::new(),
+    };
+
+    let file = match config_path() {
+        Some(path) => match fs::read_to_string(&path) {
+            Ok(contents) => {
+                let file: FileConfig = match toml::from_str(&contents) {
+                    Ok(file) => file,
+                    Err(error) => return Err(ConfigError::Parse(path, error)),
+                };
+
+                resolved.file = Some(path);
+                file
+            }
+            // not having a config file is fine, everything has a default
+            Err(error) if error.kind() == io::ErrorKind::NotFound => FileConfig::default(),
+            Err(error) => return Err(ConfigError::Read(path, error)),
+        },
+        None => FileConfig::default(),
+    };
+
+    let from_file = match &resolved.file {
+        Some(path) => format!("file ({})", path.display()),
+        None => String::from("file"),
+    };
+
+    let config = &mut resolved.config;
+    let sources = &mut resolved.sources;
+
+    resolve(
+        &mut config.tmux_bin,
+        sources,
+        "tmux_bin",
+        (file.tmux_bin, &from_file),
+        "TM_TMUX_BIN",
+    )?;
+    resolve(
+        &mut config.confirm_timeout,
+        sources,
+        "confirm_timeout",
+        (file.confirm_timeout, &from_file),
+        "TM_CONFIRM_TIMEOUT",
+    )?;
+    resolve(
+        &mut config.confirm_default,
+        sources,
+        "confirm_default",
+        (file.confirm_default, &from_file),
+        "TM_CONFIRM_DEFAULT",
+    )?;
+    resolve(
+        &mut config.default_selection,
+        sources,
+        "default_selection",
+        (file.default_selection, &from_file),
+        "TM_DEFAULT_SELECTION",
+    )?;
+    resolve(
+        &mut config.max_name_length,
+        sources,
+        "max_name_length",
+        (file.max_name_length, &from_file),
+        "TM_MAX_NAME_LENGTH",
+    )?;
+    resolve(
+        &mut config.name_template,
+        sources,
+        "name_template",
+        (file.name_template, &from_file),
+        "TM_NAME_TEMPLATE",
+    )?;
+    resolve(
+        &mut config.name_from_manifest,
+        sources,
+        "name_from_manifest",
+        (file.name_from_manifest, &from_file),
+        "TM_NAME_FROM_MANIFEST",
+    )?;
+    resolve(
+        &mut config.name_policy,
+        sources,
+        "name_policy",
+        (file.name_policy, &from_file),
+        "TM_NAME_POLICY",
+    )?;
+
+    // better to hear about a broken pattern now than at the first name typed in
+    if !config.name_policy.is_empty() {
+        if let Err(error) = regex::Regex::new(&config.name_policy) {
+            return Err(ConfigError::NamePolicy(config.name_policy.clone(), error));
+        }
+    }
+
+    resolve(
+        &mut config.copy_env,
+        sources,
+        "copy_env",
+        (file.copy_env, &from_file),
+        "TM_COPY_ENV",
+    )?;
+    resolve_from_file(
+        &mut config.copy_env_vars,
+        sources,
+        "copy_env_vars",
+        (file.copy_env_vars, &from_file),
+    );
+    resolve(
+        &mut config.grab,
+        sources,
+        "grab",
+        (file.grab, &from_file),
+        "TM_GRAB",
+    )?;
+    resolve(
+        &mut config.preview,
+        sources,
+        "preview",
+        (file.preview, &from_file),
+        "TM_PREVIEW",
+    )?;
+    resolve(
+        &mut config.shell,
+        sources,
+        "shell",
+        (file.shell, &from_file),
+        "TM_SHELL",
+    )?;
+    resolve(
+        &mut config.terminal,
+        sources,
+        "terminal",
+        (file.terminal, &from_file),
+        "TM_TERMINAL",
+    )?;
+    resolve(
+        &mut config.terminal_exec,
+        sources,
+        "terminal_exec",
+        (file.terminal_exec, &from_file),
+        "TM_TERMINAL_EXEC",
+    )?;
+    resolve(
+        &mut config.watch_interval,
+        sources,
+        "watch_interval",
+        (file.watch_interval, &from_file),
+        "TM_WATCH_INTERVAL",
+    )?;
+    resolve(
+        &mut config.no_tips,
+        sources,
+        "no_tips",
+        (file.no_tips, &from_file),
+        "TM_NO_TIPS",
+    )?;
+    resolve(
+        &mut config.record_errors,
+        sources,
+        "record_errors",
+        (file.record_errors, &from_file),
+        "TM_RECORD_ERRORS",
+    )?;
+    resolve_from_file(
+        &mut config.templates,
+        sources,
+        "templates",
+        (file.templates, &from_file),
+    );
+    resolve_from_file(
+        &mut config.aliases,
+        sources,
+        "aliases",
+        (file.aliases, &from_file),
+    );
+
+    Ok(resolved)
+}
+
+/// Make `config` the configuration returned by `get`
+pub fn init(config: Config) {
+    let _ = CONFIG.set(config);
+}
+
+/// The active configuration, the defaults if `init` hasn't been called
+pub fn get() -> &'static Config {
+    CONFIG.get_or_init(Config::default)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_env_takes_the_usual_switch_words_for_a_bool() {
+        for value in ["1", "yes", "YES", "true", "True"] {
+            assert_eq!(parse_env::<bool>(value), Some(true), "{}", value);
+        }
+        for value in ["0", "no", "No", "false", "FALSE"] {
+            assert_eq!(parse_env::<bool>(value), Some(false), "{}", value);
+        }
+    }
+
+    #[test]
+    fn parse_env_refuses_anything_else_for_a_bool() {
+        assert_eq!(parse_env::<bool>("on"), None);
+        assert_eq!(parse_env::<bool>(""), None);
+    }
+
+    #[test]
+    fn parse_env_leaves_other_values_as_they_are() {
+        assert_eq!(parse_env::<u64>("1"), Some(1));
+        assert_eq!(parse_env::<String>("yes"), Some(String::from("yes")));
+        assert_eq!(parse_env::<ConfirmDefault>("no"), Some(ConfirmDefault::No));
+        assert_eq!(parse_env::<ConfirmDefault>("0"), None);
+    }
+}
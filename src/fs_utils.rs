@@ -0,0 +1,335 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+use crate::prompt;
+
+/// Make `path` absolute by resolving it against the current directory
+pub fn absolute(path: &Path) -> PathBuf {
+    if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        match env::current_dir() {
+            Ok(current) => current.join(path),
+            Err(_error) => path.to_path_buf(),
+        }
+    }
+}
+
+/// The absolute path of `path` if it's a file that can be run
+pub fn executable(path: &Path) -> io::Result<PathBuf> {
+    let path = absolute(path);
+    let metadata = fs::metadata(&path)?;
+
+    if !metadata.is_file() {
+        return Err(io::Error::other("not a file"));
+    }
+
+    // any of the execute bits will do, tmux runs it as whoever we are
+    if metadata.permissions().mode() & 0o111 == 0 {
+        return Err(io::Error::other("not executable"));
+    }
+
+    Ok(path)
+}
+
+/// Where the program `command` is, looked up in `$PATH` the way a shell would
+///
+/// A `command` with a `/` in it is a path already and is only checked
+pub fn find_program(command: &str) -> Option<PathBuf> {
+    if command.contains('/') {
+        return executable(Path::new(command)).ok();
+    }
+
+    env::split_paths(&env::var_os("PATH")?)
+        .filter(|directory| !directory.as_os_str().is_empty())
+        .find_map(|directory| executable(&directory.join(command)).ok())
+}
+
+/// `path` made absolute without `..` or symlinks, or as it is if that can't be done
+///
+/// It can't when the path doesn't exist (any more), so this is for showing
+/// and checking paths that came from elsewhere
+pub fn canonical_or_raw(path: &str) -> String {
+    match fs::canonicalize(path) {
+        Ok(canonical) => canonical.to_string_lossy().into_owned(),
+        Err(_error) => path.to_string(),
+    }
+}
+
+/// The longest leading part of an absolute `path` that exists
+///
+/// The walk starts from whatever root the path has, `/` on Unix or a drive
+/// prefix like `C:\` on Windows, so it's empty only if not even that exists.
+pub fn valid_up_to(path: &Path) -> PathBuf {
+    let mut last_good = PathBuf::new();
+    let mut current = PathBuf::new();
+
+    for component in path.components() {
+        current.push(component);
+
+        if current.exists() {
+            last_good = current.clone();
+        } else {
+            break;
+        }
+    }
+
+    last_good
+}
+
+/// Keep asking for a directory until one that exists is entered
+///
+/// Returns `None` if the input runs out before that happens
+pub fn prompt_valid_path() -> Option<PathBuf> {
+    loop {
+        let dir_path = prompt::ask("Enter directory path: ")?;
+
+        match check_dir(&dir_path) {
+            Ok(path) => return Some(path),
+            Err(full_path) => explain_not_dir(&full_path),
+        }
+    }
+}
+
+/// `input` with a leading `~` and any `$VAR` or `${VAR}` expanded, like a shell would
+///
+/// Only a `~` at the very start stands for `$HOME`, and only when it's on its
+/// own or followed by `/`. Variables that aren't set are left as written.
+pub fn expand_path(input: &str) -> String {
+    let home = env::var("HOME").ok();
+    let input = expand_home(input, home.as_deref());
+
+    let mut expanded = String::new();
+    let mut rest = input.as_str();
+
+    while let Some(start) = rest.find('$') {
+        expanded.push_str(&rest[..start]);
+        rest = &rest[start + 1..];
+
+        let (name, after) = match rest.strip_prefix('{') {
+            Some(braced) => match braced.find('}') {
+                Some(end) => (&braced[..end], &braced[end + 1..]),
+                None => ("", rest),
+            },
+            None => {
+                let end = rest
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .unwrap_or(rest.len());
+
+                (&rest[..end], &rest[end..])
+            }
+        };
+
+        match env::var(name) {
+            Ok(value) if !name.is_empty() => {
+                expanded.push_str(&value);
+                rest = after;
+            }
+            // not a variable, or not one that's set, so the `$` stays
+            _ => expanded.push('$'),
+        }
+    }
+
+    expanded.push_str(rest);
+    expanded
+}
+
+/// `input` with a leading `~` replaced by `home`, see `expand_path`
+fn expand_home(input: &str, home: Option<&str>) -> String {
+    match (
+        input.strip_prefix('~'),
+        home.filter(|home| !home.is_empty()),
+    ) {
+        (Some(rest), Some(home)) if rest.is_empty() || rest.starts_with('/') => {
+            format!("{}{}", home, rest)
+        }
+        _ => input.to_string(),
+    }
+}
+
+/// The directory `input` names, made absolute
+///
+/// `~` and variables are expanded first, and any `..` or symlinks are tidied
+/// up so the suggested name makes sense. When it isn't a directory the
+/// absolute path is returned as the error.
+pub fn check_dir(input: &str) -> Result<PathBuf, PathBuf> {
+    let full_path = absolute(&PathBuf::from(expand_path(input.trim())));
+
+    if full_path.is_dir() {
+        Ok(fs::canonicalize(&full_path).unwrap_or(full_path))
+    } else {
+        Err(full_path)
+    }
+}
+
+/// Say how much of `full_path` exists and what could come next
+pub fn explain_not_dir(full_path: &Path) {
+    let last_good = valid_up_to(full_path);
+
+    prompt::say(&format!("{} is not a directory", full_path.display()));
+    prompt::say(&format!("Valid up to: {}", last_good.display()));
+
+    let subdirectories = subdirectories(&last_good, HINT_LIMIT + 1);
+
+    if !subdirectories.is_empty() {
+        let shown = subdirectories.len().min(HINT_LIMIT);
+
+        prompt::say(&format!("Directories in {}:", last_good.display()));
+        subdirectories[..shown]
+            .iter()
+            .for_each(|name| prompt::say(&format!("  {}", name)));
+
+        if subdirectories.len() > HINT_LIMIT {
+            prompt::say("  ...");
+        }
+    }
+}
+
+/// How many directories to show when hinting where a path could go next
+const HINT_LIMIT: usize = 20;
+
+/// Up to `limit` names of the directories directly inside `path`, sorted
+///
+/// Anything that can't be read (e.g. no permission) just isn't listed
+pub fn subdirectories(path: &Path, limit: usize) -> Vec<String> {
+    let mut names: Vec<String> = match fs::read_dir(path) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().is_dir())
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .collect(),
+        Err(_error) => Vec::new(),
+    };
+
+    names.sort();
+    names.truncate(limit);
+
+    names
+}
+
+/// How many directories `browse` shows at a time
+const PAGE_SIZE: usize = 20;
+
+/// Walk the directories under `root` and pick one
+///
+/// The directories inside the current one are listed numbered, a number
+/// goes into that one, `..` goes up, `>` and `<` page through long lists and
+/// an empty answer picks the current directory. Returns `None` on `q` or
+/// once the input runs out.
+pub fn browse(root: &Path) -> Option<PathBuf> {
+    let mut current = root.to_path_buf();
+    let mut page = 0;
+
+    loop {
+        // say why rather than showing an empty directory
+        if let Err(error) = fs::read_dir(&current) {
+            prompt::say(&format!("Can't read {}: {}", current.display(), error));
+
+            match current.parent() {
+                Some(parent) => current = parent.to_path_buf(),
+                None => return None,
+            }
+
+            continue;
+        }
+
+        let names = subdirectories(&current, usize::MAX);
+        let pages = names.len().div_ceil(PAGE_SIZE).max(1);
+        let page_start = page * PAGE_SIZE;
+
+        prompt::say(&current.display().to_string());
+        names
+            .iter()
+            .enumerate()
+            .skip(page_start)
+            .take(PAGE_SIZE)
+            .for_each(|(index, name)| prompt::say(&format!("  {}) {}", index + 1, name)));
+
+        if pages > 1 {
+            prompt::say(&format!(
+                "  (page {} of {}, > and < to page)",
+                page + 1,
+                pages
+            ));
+        }
+
+        let answer = prompt::ask("Number to open, .. to go up, Enter to use this directory: ")?;
+
+        match answer.as_str() {
+            "" => return Some(current),
+            "q" => return None,
+            ".." => {
+                if let Some(parent) = current.parent() {
+                    current = parent.to_path_buf();
+                    page = 0;
+                }
+            }
+            ">" if page + 1 < pages => page += 1,
+            "<" if page > 0 => page -= 1,
+            ">" | "<" => prompt::say("There's no page that way."),
+            answer => match answer.parse::<usize>() {
+                Ok(number) if number >= 1 && number <= names.len() => {
+                    current.push(&names[number - 1]);
+                    page = 0;
+                }
+                _ => prompt::say(&format!("Enter a number between 1 and {}", names.len())),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expand_home_replaces_a_lone_tilde() {
+        assert_eq!(expand_home("~", Some("/home/me")), "/home/me");
+    }
+
+    #[test]
+    fn expand_home_replaces_a_leading_tilde_directory() {
+        assert_eq!(expand_home("~/src/tm", Some("/home/me")), "/home/me/src/tm");
+    }
+
+    #[test]
+    fn expand_home_leaves_other_tildes_alone() {
+        assert_eq!(expand_home("a~b", Some("/home/me")), "a~b");
+        assert_eq!(expand_home("~other/src", Some("/home/me")), "~other/src");
+        assert_eq!(expand_home("/tmp/~", Some("/home/me")), "/tmp/~");
+    }
+
+    #[test]
+    fn expand_home_needs_a_home() {
+        assert_eq!(expand_home("~/src", None), "~/src");
+        assert_eq!(expand_home("~/src", Some("")), "~/src");
+    }
+
+    #[test]
+    fn expand_path_leaves_unset_variables_as_written() {
+        assert_eq!(
+            expand_path("/tmp/$TM_TEST_NEVER_SET/${TM_TEST_NEVER_SET}"),
+            "/tmp/$TM_TEST_NEVER_SET/${TM_TEST_NEVER_SET}"
+        );
+        assert_eq!(expand_path("a~b"), "a~b");
+    }
+
+    #[test]
+    fn valid_up_to_stops_at_the_first_missing_directory() {
+        let existing = env::temp_dir();
+        let missing = existing.join("tm-no-such-dir").join("below");
+
+        assert_eq!(valid_up_to(&missing), existing);
+        assert_eq!(valid_up_to(&existing), existing);
+        assert_eq!(valid_up_to(Path::new("/tm-no-such-dir")), Path::new("/"));
+    }
+
+    #[test]
+    fn valid_up_to_finds_no_root_in_a_windows_path_on_unix() {
+        // `C:\Users` is a single relative file name here, not a drive and a directory
+        assert_eq!(valid_up_to(Path::new("C:\\Users\\nobody")), PathBuf::new());
+    }
+}
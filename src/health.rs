@@ -0,0 +1,200 @@
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process;
+
+use crate::config::{self, Config};
+use crate::fs_utils;
+use crate::paths;
+use crate::term::{self, Color};
+use crate::tmux;
+
+/// How one check of `--health` came out, with what it found
+pub enum Outcome {
+    Pass(String),
+    /// something that only some features need is missing
+    Warn(String),
+    /// `tm` can't work like this
+    Fail(String),
+}
+
+/// `--health`
+///
+/// Runs every check and prints how each went, then exits with 1 if any of
+/// them failed. Only looks, nothing is written or started, not even the
+/// tmux server.
+pub fn run() {
+    let checks = [
+        ("config", check_config()),
+        ("tmux", check_tmux()),
+        ("server", check_server()),
+        ("state directory", check_state_dir()),
+        ("$TMUX", check_inside_tmux()),
+        ("$TERMINAL", check_terminal()),
+        ("$EDITOR", check_editor()),
+    ];
+
+    let mut failed = 0;
+    let mut warned = 0;
+
+    for (name, outcome) in &checks {
+        let (label, message) = match outcome {
+            Outcome::Pass(message) => (term::paint("ok  ", Color::Green), message),
+            Outcome::Warn(message) => {
+                warned += 1;
+                (term::paint("warn", Color::Yellow), message)
+            }
+            Outcome::Fail(message) => {
+                failed += 1;
+                (term::paint("FAIL", Color::Red), message)
+            }
+        };
+
+        println!("{} {}: {}", label, name, message);
+    }
+
+    println!();
+
+    if failed > 0 {
+        println!("{} of {} checks failed", failed, checks.len());
+        process::exit(1);
+    } else if warned > 0 {
+        println!("No checks failed, {} warning(s)", warned);
+    } else {
+        println!("All checks passed");
+    }
+}
+
+/// Whether the config file can be used, making it the active config when it can
+///
+/// This runs first so the other checks look at the tmux the config picks
+fn check_config() -> Outcome {
+    match config::load() {
+        Ok(resolved) => {
+            let outcome = match &resolved.file {
+                Some(file) => Outcome::Pass(format!("{} parses", file.display())),
+                None => Outcome::Pass(String::from("no config file, using the defaults")),
+            };
+
+            config::init(resolved.config);
+            outcome
+        }
+        Err(error) => {
+            config::init(Config::default());
+            Outcome::Fail(format!("{}, the other checks use the defaults", error))
+        }
+    }
+}
+
+/// Whether the configured tmux can be found and run, and which version it is
+fn check_tmux() -> Outcome {
+    let tmux_bin = &config::get().tmux_bin;
+
+    let path = match fs_utils::find_program(tmux_bin) {
+        Some(path) => path,
+        None => return Outcome::Fail(format!("{} not found, is tmux installed?", tmux_bin)),
+    };
+
+    // tmux -V
+    match scuttle::run_output(&tmux::app(&["-V"])) {
+        Ok(output) if output.status.success() => Outcome::Pass(format!(
+            "{} ({})",
+            path.display(),
+            String::from_utf8_lossy(&output.stdout).trim()
+        )),
+        Ok(output) => Outcome::Fail(format!(
+            "{} -V failed: {}",
+            path.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )),
+        Err(error) => Outcome::Fail(format!("could not run {}: {}", path.display(), error)),
+    }
+}
+
+/// Whether a tmux server is running and how many sessions it has
+fn check_server() -> Outcome {
+    let output = match tmux::list_sessions_output() {
+        Ok(output) => output,
+        Err(error) => return Outcome::Warn(format!("could not ask tmux: {}", error)),
+    };
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    if output.status.success() {
+        let count = String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .count();
+
+        Outcome::Pass(format!("running with {} session(s)", count))
+    } else if tmux::is_no_server(&stderr) {
+        // not a problem, the first new session starts one
+        Outcome::Pass(String::from("not running"))
+    } else {
+        Outcome::Warn(format!("not answering: {}", stderr.trim()))
+    }
+}
+
+/// Whether bookmarks and the like could be kept, without writing anything to find out
+fn check_state_dir() -> Outcome {
+    let state = match paths::state_dir() {
+        Some(state) => state,
+        None => {
+            return Outcome::Warn(String::from(
+                "neither $XDG_STATE_HOME nor $HOME is set, bookmarks can't be kept",
+            ))
+        }
+    };
+
+    // it's created on first use, so until then it's whether it could be created that matters
+    let existing = state
+        .ancestors()
+        .find(|ancestor| ancestor.exists())
+        .unwrap_or(Path::new("/"));
+
+    match fs::metadata(existing) {
+        Ok(metadata) if !metadata.is_dir() => Outcome::Warn(format!(
+            "{} is in the way of {}, it isn't a directory",
+            existing.display(),
+            state.display()
+        )),
+        Ok(metadata) if metadata.permissions().readonly() => {
+            Outcome::Warn(format!("{} isn't writable", existing.display()))
+        }
+        Ok(_metadata) if existing == state => Outcome::Pass(state.display().to_string()),
+        Ok(_metadata) => Outcome::Pass(format!("{} (created when needed)", state.display())),
+        Err(error) => Outcome::Warn(format!("{}: {}", existing.display(), error)),
+    }
+}
+
+/// Whether `tm` is running inside tmux, which changes how some things work
+fn check_inside_tmux() -> Outcome {
+    match env::var("TMUX") {
+        Ok(tmux) if !tmux.is_empty() => Outcome::Pass(format!("inside tmux ({})", tmux)),
+        _ => Outcome::Pass(String::from("not inside tmux")),
+    }
+}
+
+/// Whether there's a terminal to open sessions in with `--spawn`
+fn check_terminal() -> Outcome {
+    let configured = &config::get().terminal;
+
+    if !configured.trim().is_empty() {
+        return Outcome::Pass(format!("not needed, terminal is set to {}", configured));
+    }
+
+    match env::var("TERMINAL") {
+        Ok(terminal) if !terminal.trim().is_empty() => Outcome::Pass(terminal),
+        _ => Outcome::Warn(String::from(
+            "not set, --spawn needs it or the terminal setting",
+        )),
+    }
+}
+
+/// Whether there's an editor for `e<number>` at the menu
+fn check_editor() -> Outcome {
+    match env::var("EDITOR") {
+        Ok(editor) if !editor.trim().is_empty() => Outcome::Pass(editor),
+        _ => Outcome::Warn(String::from("not set, e<number> at the menu needs it")),
+    }
+}
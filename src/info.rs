@@ -0,0 +1,67 @@
+use std::io;
+
+use serde::Serialize;
+
+use crate::time_utils;
+use crate::tmux;
+
+/// Everything worth knowing about one session
+#[derive(Debug, Serialize)]
+pub struct SessionInfo {
+    pub name: String,
+    /// the directory the session was started in
+    pub path: String,
+    pub windows: usize,
+    /// unix time
+    pub created: u64,
+    /// unix time, `None` if it has never been attached
+    pub last_attached: Option<u64>,
+    pub window_names: Vec<String>,
+}
+
+/// Gather the details of `name`, `None` if there's no such session
+pub fn gather(name: &str) -> io::Result<Option<SessionInfo>> {
+    // with no server running there are no sessions to describe
+    let sessions = tmux::running_sessions();
+
+    let session = match sessions
+        .sessions
+        .into_iter()
+        .find(|session| session.name == name)
+    {
+        Some(session) => session,
+        None => return Ok(None),
+    };
+
+    let path = tmux::session_path(&session.name)?;
+    let windows = tmux::list_windows(Some(&session.name))?;
+
+    Ok(Some(SessionInfo {
+        path,
+        windows: session.windows,
+        created: session.created,
+        last_attached: Some(session.last_attached).filter(|time| *time > 0),
+        window_names: windows.into_iter().map(|window| window.name).collect(),
+        name: session.name,
+    }))
+}
+
+/// Render `info` as aligned `key: value` lines
+pub fn render(info: &SessionInfo) -> String {
+    let last_attached = match info.last_attached {
+        Some(time) => time_utils::format_timestamp(time),
+        None => String::from("never"),
+    };
+
+    [
+        ("name", info.name.clone()),
+        ("path", info.path.clone()),
+        ("windows", info.windows.to_string()),
+        ("created", time_utils::format_timestamp(info.created)),
+        ("last attached", last_attached),
+        ("window names", info.window_names.join(", ")),
+    ]
+    .iter()
+    .map(|(key, value)| format!("{:<14} {}\n", format!("{}:", key), value))
+    .collect()
+}
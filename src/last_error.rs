@@ -0,0 +1,75 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::process::Output;
+
+use serde::{Deserialize, Serialize};
+
+use crate::commands;
+use crate::config;
+use crate::paths;
+use crate::report;
+
+/// A tmux command that failed, as kept for `--dump-last-error`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Failure {
+    pub command: String,
+    pub args: Vec<String>,
+    /// `None` when tmux was ended by a signal
+    pub code: Option<i32>,
+    pub stderr: String,
+}
+
+/// Where the last failure is kept, usually `~/.local/state/tm/last-error.json`
+pub fn last_error_path() -> Option<PathBuf> {
+    paths::state_dir().map(|directory| directory.join("last-error.json"))
+}
+
+/// Keep `app` and what it printed as the last failure, when `record_errors` is on
+///
+/// This only ever helps explain an error that is already being reported, so
+/// if it can't be written it's left at that
+pub fn record(app: &scuttle::App, output: &Output) {
+    if !config::get().record_errors {
+        return;
+    }
+
+    let failure = Failure {
+        command: app.command.clone(),
+        args: app.args.clone(),
+        code: output.status.code(),
+        stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+    };
+
+    if let (Some(path), Ok(json)) = (last_error_path(), serde_json::to_string(&failure)) {
+        let _ = paths::prepare(path).and_then(|path| fs::write(path, json));
+    }
+}
+
+/// Print the last failure that was kept, or say there isn't one
+pub fn dump() {
+    let path = match last_error_path() {
+        Some(path) => path,
+        None => report::fail(
+            "neither $XDG_STATE_HOME nor $HOME is set, don't know where errors are kept",
+            1,
+        ),
+    };
+
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => {
+            println!("no recorded error");
+            return;
+        }
+        Err(error) => report::fail(format!("{}: {}", path.display(), error), 1),
+    };
+
+    match serde_json::from_str::<Failure>(&contents) {
+        Ok(failure) => commands::print_json(&failure),
+        Err(error) => report::fail(
+            format!("invalid error record in {}: {}", path.display(), error),
+            1,
+        ),
+    }
+}
@@ -0,0 +1,296 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::sessions::{SessionList, Window};
+use crate::tmux;
+
+/// The layout file format written by this version of `tm`
+///
+/// Bump this when the format changes in a way older versions can't read,
+/// new optional fields should get `#[serde(default)]` instead.
+pub const VERSION: u32 = 1;
+
+/// Every session and its windows, as saved to a layout file
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Layout {
+    pub version: u32,
+    pub sessions: Vec<SessionLayout>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SessionLayout {
+    pub name: String,
+    /// in the order tmux lists them
+    pub windows: Vec<WindowLayout>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WindowLayout {
+    pub name: String,
+    /// working directory of the window's active pane
+    pub path: String,
+}
+
+#[derive(Debug)]
+pub enum LayoutError {
+    /// tmux or the layout file couldn't be read or written
+    Io(io::Error),
+    Json(serde_json::Error),
+    /// the file was written by a newer `tm`
+    Version(u32),
+}
+
+impl fmt::Display for LayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LayoutError::Io(error) => write!(f, "{}", error),
+            LayoutError::Json(error) => write!(f, "invalid layout file: {}", error),
+            LayoutError::Version(version) => write!(
+                f,
+                "layout file is version {} but this tm only understands up to {}",
+                version, VERSION
+            ),
+        }
+    }
+}
+
+impl From<io::Error> for LayoutError {
+    fn from(error: io::Error) -> Self {
+        LayoutError::Io(error)
+    }
+}
+
+impl From<serde_json::Error> for LayoutError {
+    fn from(error: serde_json::Error) -> Self {
+        LayoutError::Json(error)
+    }
+}
+
+/// What `restore` did with each saved session
+pub enum Restored {
+    /// the session was created with this many windows
+    Created(String, usize),
+    /// a session with this name was already running and was left alone
+    Skipped(String),
+}
+
+/// How a saved session compares to what's running
+#[derive(Debug, PartialEq, Eq)]
+pub enum Change {
+    /// it isn't running and would be created with this many windows
+    Create(String, usize),
+    /// it's running with the same windows and would be left alone
+    Unchanged(String),
+    /// it's running but its windows differ, it would still be left alone
+    Differs {
+        name: String,
+        /// saved window names that aren't in the running session
+        missing: Vec<String>,
+        /// running window names that weren't saved
+        extra: Vec<String>,
+    },
+}
+
+/// Compare `layout` against the running `sessions` and their `windows`
+pub fn plan(layout: &Layout, sessions: &SessionList, windows: &[Window]) -> Vec<Change> {
+    let running = sessions.names();
+
+    layout
+        .sessions
+        .iter()
+        .map(|session| {
+            if !running.contains(&session.name.as_str()) {
+                return Change::Create(session.name.clone(), session.windows.len().max(1));
+            }
+
+            let current: Vec<&str> = windows
+                .iter()
+                .filter(|window| window.session == session.name)
+                .map(|window| window.name.as_str())
+                .collect();
+            let saved: Vec<&str> = session
+                .windows
+                .iter()
+                .map(|window| window.name.as_str())
+                .collect();
+
+            let missing: Vec<String> = saved
+                .iter()
+                .filter(|name| !current.contains(name))
+                .map(|name| name.to_string())
+                .collect();
+            let extra: Vec<String> = current
+                .iter()
+                .filter(|name| !saved.contains(name))
+                .map(|name| name.to_string())
+                .collect();
+
+            if missing.is_empty() && extra.is_empty() {
+                Change::Unchanged(session.name.clone())
+            } else {
+                Change::Differs {
+                    name: session.name.clone(),
+                    missing,
+                    extra,
+                }
+            }
+        })
+        .collect()
+}
+
+/// Work out what `restore` would do with `layout` without doing it
+pub fn preview(layout: &Layout) -> io::Result<Vec<Change>> {
+    // with no server running nothing exists yet
+    let sessions = tmux::running_sessions();
+    let windows = if sessions.sessions.is_empty() {
+        Vec::new()
+    } else {
+        tmux::list_windows(None)?
+    };
+
+    Ok(plan(layout, &sessions, &windows))
+}
+
+/// Capture the sessions and windows tmux is running right now
+pub fn capture() -> io::Result<Layout> {
+    let sessions = tmux::list_sessions()?;
+    let windows = tmux::list_windows(None)?;
+
+    let sessions = sessions
+        .sessions
+        .iter()
+        .map(|session| SessionLayout {
+            name: session.name.clone(),
+            windows: windows
+                .iter()
+                .filter(|window| window.session == session.name)
+                .map(|window| WindowLayout {
+                    name: window.name.clone(),
+                    path: window.path.clone(),
+                })
+                .collect(),
+        })
+        .collect();
+
+    Ok(Layout {
+        version: VERSION,
+        sessions,
+    })
+}
+
+/// Capture the windows of the running session `name`
+pub fn capture_session(name: &str) -> io::Result<SessionLayout> {
+    let windows = tmux::list_windows(Some(name))?;
+
+    Ok(SessionLayout {
+        name: name.to_string(),
+        windows: windows
+            .into_iter()
+            .map(|window| WindowLayout {
+                name: window.name,
+                path: window.path,
+            })
+            .collect(),
+    })
+}
+
+/// Start a new session `name` with the same windows as `source`, detached
+///
+/// Only the window names and directories are copied, tmux has no reliable
+/// way to tell what was started in them. Returns how many windows it has.
+pub fn clone(source: &str, name: &str) -> io::Result<usize> {
+    let mut session = capture_session(source)?;
+
+    session.name = name.to_string();
+    create(&session)?;
+
+    Ok(session.windows.len().max(1))
+}
+
+/// Write the current layout to `file`
+pub fn save(file: &Path) -> Result<Layout, LayoutError> {
+    let layout = capture()?;
+
+    fs::write(file, serde_json::to_string_pretty(&layout)? + "\n")?;
+
+    Ok(layout)
+}
+
+/// Read a layout previously written by `save`
+pub fn load(file: &Path) -> Result<Layout, LayoutError> {
+    let layout: Layout = serde_json::from_str(&fs::read_to_string(file)?)?;
+
+    if layout.version > VERSION {
+        return Err(LayoutError::Version(layout.version));
+    }
+
+    Ok(layout)
+}
+
+/// Create every session in `layout` that isn't already running
+pub fn restore(layout: &Layout) -> Result<Vec<Restored>, LayoutError> {
+    // there may not be a server yet, in which case nothing exists
+    let existing = tmux::running_sessions();
+    let existing = existing.names();
+    let mut restored = Vec::new();
+
+    for session in &layout.sessions {
+        if existing.contains(&session.name.as_str()) {
+            restored.push(Restored::Skipped(session.name.clone()));
+            continue;
+        }
+
+        create(session)?;
+
+        restored.push(Restored::Created(
+            session.name.clone(),
+            session.windows.len().max(1),
+        ));
+    }
+
+    Ok(restored)
+}
+
+/// Start `session` detached with its windows, in order
+fn create(session: &SessionLayout) -> io::Result<()> {
+    let mut windows = session.windows.iter();
+
+    // the session comes with its first window
+    // tmux new-session -d -s <name> [-n <window> -c <path>]
+    match windows.next() {
+        Some(first) => tmux::output(&[
+            "new-session",
+            "-d",
+            "-s",
+            &session.name,
+            "-n",
+            &first.name,
+            "-c",
+            &first.path,
+        ])?,
+        None => tmux::output(&["new-session", "-d", "-s", &session.name])?,
+    };
+
+    // then the rest are added after it in order
+    // tmux new-window -d -t <name>: -n <window> -c <path>
+    for window in windows {
+        let target = format!("{}:", session.name);
+
+        tmux::output(&[
+            "new-window",
+            "-d",
+            "-t",
+            &target,
+            "-n",
+            &window.name,
+            "-c",
+            &window.path,
+        ])?;
+    }
+
+    Ok(())
+}
@@ -1,80 +1,1521 @@
-use std::io;
-use std::io::Write;
+use std::env;
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::Duration;
+
+use regex::Regex;
 
 extern crate scuttle;
 
+mod bookmarks;
+mod cli;
+mod clipboard;
+mod commands;
+mod config;
+mod fs_utils;
+mod health;
+mod info;
+mod last_error;
+mod layout;
+mod manifest;
+mod notify;
+mod paths;
+mod process_utils;
+mod prompt;
+mod report;
+mod session_utils;
+mod sessions;
+mod term;
+mod time_utils;
+mod tmux;
+mod tree;
+mod watch;
+
+use config::DefaultSelection;
+use session_utils::NameChoice;
+use sessions::{Listing, SessionList, Sort};
+use term::{Color, ColorWhen};
+use tmux::NewSessionError;
+
 fn main() {
-    // list the available tmux sessions
-    // tmux ls -F "#S"
-    let tmux_list_sessions = scuttle::App {
-        command: String::from("tmux"),
-        args: vec!["ls".to_string(), "-F".to_string(), "#S".to_string()],
+    // looked for before anything is parsed, so that parsing errors come out as JSON too
+    if cli::wants_json_errors(env::args().collect()) {
+        report::use_json();
+    }
+
+    let matches = match cli::build().try_get_matches_from(cli::args()) {
+        Ok(matches) => matches,
+        Err(error) => cli::exit_with(error),
+    };
+
+    if matches.value_of("error-format") == Some("json") {
+        report::use_json();
+    }
+
+    report::set_verbosity(u8::try_from(matches.occurrences_of("verbose")).unwrap_or(u8::MAX));
+
+    if matches.is_present("no-color") {
+        term::use_color(ColorWhen::Never);
+    } else if let Some(when) = matches.value_of("color") {
+        match when.parse() {
+            Ok(when) => term::use_color(when),
+            Err(error) => report::fail(error, 2),
+        }
+    }
+
+    if matches.is_present("help") {
+        return cli::help(matches.value_of("help"));
+    }
+
+    if matches.is_present("init") {
+        // before loading so a broken config file can be replaced with --force
+        return commands::init(matches.is_present("force"), matches.is_present("quiet"));
+    }
+
+    if matches.is_present("health") {
+        // before loading so a broken config file is reported as one of the checks
+        return health::run();
+    }
+
+    let mut resolved = match config::load() {
+        Ok(resolved) => resolved,
+        Err(error) => {
+            report::fail(error, 2);
+        }
+    };
+
+    // flags given on the command line beat every other layer
+    if let Some(template) = matches.value_of("name-template") {
+        resolved.config.name_template = template.to_string();
+        resolved
+            .sources
+            .insert("name_template", String::from("flag (--name-template)"));
+    }
+
+    if matches.is_present("copy-env") {
+        resolved.config.copy_env = true;
+        resolved
+            .sources
+            .insert("copy_env", String::from("flag (--copy-env)"));
+    }
+
+    if matches.is_present("grab") {
+        resolved.config.grab = true;
+        resolved
+            .sources
+            .insert("grab", String::from("flag (--grab)"));
+    }
+
+    if matches.is_present("preview") {
+        resolved.config.preview = true;
+        resolved
+            .sources
+            .insert("preview", String::from("flag (--preview)"));
+    }
+
+    if let Some(shell) = matches.value_of("shell") {
+        resolved.config.shell = shell.to_string();
+        resolved
+            .sources
+            .insert("shell", String::from("flag (--shell)"));
+    }
+
+    if matches.is_present("dump-config") {
+        // print what we would run with and stop before touching tmux
+        return commands::print_json(&resolved);
+    }
+
+    if !resolved.config.shell.is_empty() {
+        // check it now rather than after the questions for a new session have been answered
+        match fs_utils::executable(Path::new(&resolved.config.shell)) {
+            Ok(shell) => resolved.config.shell = shell.to_string_lossy().into_owned(),
+            Err(error) => {
+                report::fail(
+                    format!(
+                        "can't use {} as the shell: {}",
+                        resolved.config.shell, error
+                    ),
+                    2,
+                );
+            }
+        }
+    }
+
+    let has_config_file = resolved.file.is_some();
+
+    config::init(resolved.config);
+    prompt::allow_prompts(!matches.is_present("no-interactive"));
+    first_run_tip(has_config_file);
+
+    if let Some(size) = matches.value_of("size") {
+        match size.parse() {
+            Ok(size) => tmux::resize_on_attach(size),
+            Err(error) => report::fail(format!("invalid --size: {}", error), 2),
+        }
+    }
+
+    if matches.is_present("dump-last-error") {
+        return last_error::dump();
+    }
+
+    if matches.is_present("which") {
+        return commands::which();
+    }
+
+    if let Some(command) = matches.value_of("attach-window-cmd") {
+        tmux::run_on_attach(command);
+    }
+
+    match matches.subcommand() {
+        Some(("attach", attach)) => {
+            if let Some(name) = attach.value_of("name") {
+                return commands::attach(name);
+            }
+        }
+        Some(("kill", kill)) => {
+            return commands::kill(
+                kill.value_of("name"),
+                kill.is_present("all"),
+                matches.is_present("quiet"),
+            );
+        }
+        Some(("new", new)) => {
+            if let Some(path) = new.value_of("path") {
+                return commands::new_session(
+                    path,
+                    new.value_of("name"),
+                    !new.is_present("no-attach"),
+                    matches.is_present("quiet"),
+                );
+            }
+        }
+        _ => (),
+    }
+
+    let json = matches.is_present("json");
+
+    if let Some(name) = matches.value_of("session-info") {
+        return commands::session_info(name, json);
+    }
+
+    if let Some(name) = matches.value_of("detach") {
+        return commands::detach(
+            name,
+            matches.is_present("then-attach"),
+            matches.is_present("quiet"),
+        );
+    }
+
+    if let Some(name) = matches.value_of("capture") {
+        return commands::capture(name, matches.is_present("capture-all"));
+    }
+
+    if let Some(mut values) = matches.values_of("select-window-by-name") {
+        // clap makes sure both are there
+        let session = values.next().unwrap_or_default();
+        let window = values.next().unwrap_or_default();
+
+        return commands::select_window_by_name(session, window);
+    }
+
+    if let Some(mut values) = matches.values_of("run") {
+        // clap makes sure both are there
+        let name = values.next().unwrap_or_default();
+        let command = values.next().unwrap_or_default();
+
+        return commands::run(name, command, matches.is_present("new-window"));
+    }
+
+    if matches.is_present("new-window") {
+        return commands::new_window(matches.value_of("new-window"));
+    }
+
+    if let Some(mut values) = matches.values_of("send-keys") {
+        // clap makes sure there's a name and at least one key
+        let name = values.next().unwrap_or_default();
+        let keys: Vec<&str> = values.collect();
+
+        return commands::send_keys(name, &keys);
+    }
+
+    if let Some(mut values) = matches.values_of("set-option") {
+        // clap makes sure all three are there
+        let name = values.next().unwrap_or_default();
+        let option = values.next().unwrap_or_default();
+        let value = values.next().unwrap_or_default();
+
+        return commands::set_option(name, option, value);
+    }
+
+    if let Some(name) = matches.value_of("spawn") {
+        return commands::spawn(name);
+    }
+
+    if matches.is_present("all-sockets") {
+        return all_sockets();
+    }
+
+    if let Some(name) = matches.value_of("replace") {
+        return replace_session(name, matches.is_present("must-exist"));
+    }
+
+    if let Some(group) = matches.value_of("group-with") {
+        return group_with(group);
+    }
+
+    if let Some(root) = matches.value_of("browse") {
+        return browse(root);
+    }
+
+    if matches.is_present("from-clipboard") {
+        return from_clipboard();
+    }
+
+    if let Some(name) = matches.value_of("rename-interactive") {
+        return commands::rename_interactive(name);
+    }
+
+    if let Some(name) = matches.value_of("rename-from-dir") {
+        return commands::rename_from_dir(name);
+    }
+
+    if matches.is_present("rename-session-interactive") {
+        return commands::rename_sessions();
+    }
+
+    if let Some(mut values) = matches.values_of("note") {
+        // clap makes sure both are there
+        let name = values.next().unwrap_or_default();
+        let note = values.next().unwrap_or_default();
+
+        return commands::note(name, Some(note));
+    }
+
+    if let Some(name) = matches.value_of("clear-note") {
+        return commands::note(name, None);
+    }
+
+    if matches.is_present("tree") {
+        return commands::tree();
+    }
+
+    if matches.is_present("watch") {
+        // a zero interval would redraw as fast as tmux can answer
+        let interval = config::get().watch_interval.max(1);
+
+        let active_only = matches.is_present("active-only");
+
+        if json {
+            return watch::run_json(Duration::from_secs(interval), active_only);
+        }
+
+        return watch::run(Duration::from_secs(interval), active_only);
+    }
+
+    if let Some(mut values) = matches.values_of("merge") {
+        // clap makes sure both are there
+        let source = values.next().unwrap_or_default();
+        let destination = values.next().unwrap_or_default();
+
+        return commands::merge(source, destination);
+    }
+
+    if matches.is_present("link-window") {
+        return commands::link_window();
+    }
+
+    if matches.is_present("templates-list") {
+        return commands::templates_list(json);
+    }
+
+    if let Some(file) = matches.value_of("export-bookmarks") {
+        return commands::export_bookmarks(Path::new(file));
+    }
+
+    if let Some(file) = matches.value_of("import-bookmarks") {
+        return commands::import_bookmarks(Path::new(file));
+    }
+
+    if let Some(file) = matches.value_of("save-layout") {
+        return commands::save_layout(Path::new(file), matches.is_present("quiet"));
+    }
+
+    if let Some(mut values) = matches.values_of("clone-layout") {
+        // clap makes sure both are there
+        let source = values.next().unwrap_or_default();
+        let name = values.next().unwrap_or_default();
+
+        return commands::clone_layout(source, name, !matches.is_present("no-attach"));
+    }
+
+    if let Some(file) = matches.value_of("restore-layout") {
+        commands::restore_layout(
+            Path::new(file),
+            matches.is_present("dry-run"),
+            !matches.is_present("no-preview"),
+            matches.is_present("quiet"),
+        );
+
+        if matches.is_present("notify") {
+            notify::done(&format!("Finished restoring {}", file));
+        }
+
+        return;
+    }
+
+    let sort = match matches.value_of("sort").map(|sort| sort.parse::<Sort>()) {
+        Some(Ok(sort)) => Some(sort),
+        Some(Err(error)) => {
+            report::fail(error, 2);
+        }
+        None => None,
+    };
+
+    let grep = match matches.value_of("grep").map(Regex::new) {
+        Some(Ok(grep)) => Some(grep),
+        Some(Err(error)) => {
+            report::fail(format!("invalid --grep pattern: {}", error), 2);
+        }
+        None => None,
+    };
+
+    let options = ListOptions {
+        sort,
+        prefix: matches.value_of("prefix").map(String::from),
+        grep,
+        active_only: matches.is_present("active-only"),
     };
 
-    match scuttle::run_output(&tmux_list_sessions) {
+    if matches.is_present("list-names") {
+        // with no server running there are no names to print
+        let mut sessions = tmux::running_sessions();
+
+        options.apply(&mut sessions);
+
+        let stdout = io::stdout();
+
+        return commands::list_names(&mut stdout.lock(), &sessions, matches.is_present("print0"));
+    }
+
+    if let Some(command) = matches.value_of("pipe-to") {
+        return pick_with(command, &options);
+    }
+
+    if matches.is_present("interactive-kill") {
+        return interactive_kill(&options);
+    }
+
+    if matches.is_present("select") {
+        return select(&options);
+    }
+
+    // keep showing the menu for as long as the chosen action asks for it
+    while menu(&options, matches.is_present("loop")) {}
+}
+
+/// Point somebody running `tm` for the first time at where to start
+///
+/// That's when there's neither a config file nor a state directory yet. The
+/// state directory is created with a marker in it, so the tip is shown once.
+/// Scripts never see it, nor does anyone with `no_tips` set.
+fn first_run_tip(has_config_file: bool) {
+    if has_config_file || config::get().no_tips || !io::stdout().is_terminal() {
+        return;
+    }
+
+    let state = match paths::state_dir() {
+        Some(state) if !state.exists() => state,
+        _ => return,
+    };
+
+    // if the marker can't be written the tip would come back every time, better not to show it
+    if paths::prepare(state.join("first-run"))
+        .and_then(|marker| fs::write(marker, ""))
+        .is_ok()
+    {
+        println!(
+            "{}",
+            term::paint(
+                "Tip: tm --init writes a config file to start from, tm --help shows what else tm can do.",
+                Color::Dim
+            )
+        );
+    }
+}
+
+/// Let an external command like `fzf` pick the session instead of the menu
+///
+/// The session names are written to `command`'s stdin one per line and the
+/// line it prints is the session to attach to. Printing nothing means the
+/// pick was cancelled.
+fn pick_with(command: &str, options: &ListOptions) {
+    let mut sessions = match tmux::list_sessions() {
+        Ok(sessions) => sessions,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => tmux::fail_to_run(error),
+        Err(error) => {
+            report::fail(error, 1);
+        }
+    };
+
+    options.apply(&mut sessions);
+
+    let names = sessions.names();
+    let input: String = names.iter().map(|name| format!("{}\n", name)).collect();
+
+    // sh -c <command>
+    let picked = match process_utils::run_with_stdin("sh", &["-c", command], &input) {
+        Ok(output) => String::from_utf8_lossy(&output.stdout).into_owned(),
+        Err(error) => {
+            report::fail(format!("could not run {}: {}", command, error), 1);
+        }
+    };
+
+    let picked = picked.lines().next().unwrap_or_default().trim();
+
+    if picked.is_empty() {
+        return;
+    }
+
+    if names.contains(&picked) {
+        tmux::attach(picked);
+    } else {
+        report::fail(
+            format!("{} picked '{}' which isn't a session", command, picked),
+            1,
+        );
+    }
+}
+
+/// How the session list is narrowed down and ordered before it is shown
+struct ListOptions {
+    sort: Option<Sort>,
+    /// only show sessions whose name starts with this
+    prefix: Option<String>,
+    /// only show sessions whose name matches, checked after `prefix`
+    grep: Option<Regex>,
+    /// only show sessions with a client attached
+    active_only: bool,
+}
+
+impl ListOptions {
+    /// Filter and order `sessions` for display
+    ///
+    /// Exits when there were sessions but the filters left none of them
+    fn apply(&self, sessions: &mut SessionList) {
+        if let Some(prefix) = &self.prefix {
+            let any = !sessions.sessions.is_empty();
+
+            sessions.retain_prefix(prefix);
+
+            if any && sessions.sessions.is_empty() {
+                prompt::say(&format!("No sessions start with '{}'", prefix));
+                process::exit(1);
+            }
+        }
+
+        if let Some(grep) = &self.grep {
+            let any = !sessions.sessions.is_empty();
+
+            sessions.grep(grep);
+
+            if any && sessions.sessions.is_empty() {
+                prompt::say(&format!("No sessions match '{}'", grep.as_str()));
+                process::exit(1);
+            }
+        }
+
+        if self.active_only {
+            let any = !sessions.sessions.is_empty();
+
+            sessions.retain_attached();
+
+            if any && sessions.sessions.is_empty() {
+                prompt::say("No sessions have a client attached");
+                process::exit(1);
+            }
+        }
+
+        if let Some(sort) = self.sort {
+            sessions.sort(sort);
+        }
+    }
+}
+
+/// The answers the menu understands right now, on one line
+///
+/// Shown under the list and again after an answer that isn't one of them, so
+/// it only offers what can be done: `count` sessions, `e` only with $EDITOR set
+/// and Enter only when there's a `default_choice`
+fn menu_hint(count: usize, default_choice: Option<usize>) -> String {
+    let mut inputs = Vec::new();
+
+    if count > 0 {
+        let number = if count == 1 {
+            String::from("1")
+        } else {
+            format!("1-{}", count)
+        };
+
+        inputs.push(format!("{} or a name attach", number));
+
+        if env::var("EDITOR").is_ok_and(|editor| !editor.trim().is_empty()) {
+            inputs.push(String::from("e<number> edit"));
+        }
+
+        inputs.push(String::from("r<number> rename"));
+        inputs.push(String::from("k<number> kill"));
+    }
+
+    inputs.push(String::from("n new"));
+
+    if let Some(default_choice) = default_choice {
+        inputs.push(format!("Enter {}", default_choice));
+    }
+
+    inputs.push(String::from("q quit"));
+
+    inputs.join(", ")
+}
+
+/// What an answer at the menu prompt picks
+#[derive(Debug, PartialEq, Eq)]
+enum Selection {
+    /// `n`, create a new session
+    New,
+    /// attach to the session at this position in the list (0 based)
+    Index(usize),
+    /// `e<number>`, open the session's directory in $EDITOR
+    Edit(usize),
+    /// `r<number>`, rename the session
+    Rename(usize),
+    /// `k<number>`, kill the session
+    Kill(usize),
+    /// a number, but not one of the sessions
+    Invalid,
+    /// text that isn't a number, perhaps a session's name
+    Fuzzy(String),
+}
+
+/// Work out what `input` picks out of the sessions in `lines`, numbered from 1
+///
+/// A number attaches and `e`, `r` or `k` before it edits, renames or kills
+/// instead. Whitespace around the answer and a single `.` after the number,
+/// as in `2.`, are ignored. A session called exactly what was entered is
+/// picked over those actions, so one named `k2` can still be attached to,
+/// but `n` always starts a new session and a number is always a position.
+fn parse_selection(input: &str, lines: &[&str]) -> Selection {
+    let input = input.trim();
+    let count = lines.len();
+
+    if input == "n" {
+        return Selection::New;
+    }
+
+    let plain_number = input.strip_suffix('.').unwrap_or(input);
+
+    if plain_number.is_empty() || !plain_number.chars().all(|c| c.is_ascii_digit()) {
+        if let Some(index) = lines.iter().position(|line| *line == input) {
+            return Selection::Index(index);
+        }
+    }
+
+    let (action, number) = match input.chars().next() {
+        Some(action @ ('e' | 'r' | 'k')) => (Some(action), input[1..].trim_start()),
+        _ => (None, input),
+    };
+
+    let number = number.strip_suffix('.').unwrap_or(number).trim_end();
+
+    if number.is_empty() || !number.chars().all(|c| c.is_ascii_digit()) {
+        return Selection::Fuzzy(input.to_string());
+    }
+
+    let index = match number.parse::<usize>() {
+        Ok(number) if number >= 1 && number <= count => number - 1,
+        _ => return Selection::Invalid,
+    };
+
+    match action {
+        Some('e') => Selection::Edit(index),
+        Some('r') => Selection::Rename(index),
+        Some('k') => Selection::Kill(index),
+        _ => Selection::Index(index),
+    }
+}
+
+/// The sessions in `lines` whose name contains `query`, ignoring case
+///
+/// An empty query keeps them all
+fn filter_sessions<'a>(lines: &[&'a str], query: &str) -> Vec<&'a str> {
+    let query = query.to_lowercase();
+
+    lines
+        .iter()
+        .filter(|line| line.to_lowercase().contains(&query))
+        .copied()
+        .collect()
+}
+
+/// The session `input` picks to attach to out of `lines`, by its number or its exact name
+///
+/// `None` when it's neither, or when it's one of the other answers like `n` or `k2`
+fn resolve_selection(input: &str, lines: &[&str]) -> Option<String> {
+    match parse_selection(input, lines) {
+        Selection::Index(index) => Some(lines[index].to_string()),
+        Selection::Fuzzy(name) => lines
+            .iter()
+            .find(|line| **line == name)
+            .map(|line| line.to_string()),
+        _ => None,
+    }
+}
+
+/// Show the list of sessions and act on the one the user picks
+///
+/// Sessions are shown in tmux's order unless `options` says otherwise. With
+/// `hub` the menu comes back after detaching, until `q` is entered.
+/// Returns `true` when the menu should be shown again
+fn menu(options: &ListOptions, hub: bool) -> bool {
+    menu_with(
+        &mut prompt::output(),
+        &mut io::stderr(),
+        &mut prompt::ask,
+        options,
+        hub,
+    )
+}
+
+/// `menu` writing the list, hint and answers to `out`, tmux's complaints to
+/// `err` and reading the choice from `ask`
+fn menu_with(
+    out: &mut dyn Write,
+    err: &mut dyn Write,
+    ask: &mut dyn FnMut(&str) -> Option<String>,
+    options: &ListOptions,
+    hub: bool,
+) -> bool {
+    let say = |out: &mut dyn Write, line: &str| {
+        commands::exit_on_broken_pipe(writeln!(out, "{}", line));
+    };
+
+    // list the available tmux sessions
+    match tmux::list_sessions_output() {
         Ok(output) => {
+            // tmux complains here when there is no server yet, which just means no sessions
+            match std::str::from_utf8(&output.stderr) {
+                Ok(result) if !result.trim().is_empty() => say(err, result.trim_end()),
+                Ok(_result) => (),
+                Err(error) => say(err, &error.to_string()),
+            }
             match std::str::from_utf8(&output.stdout) {
                 Ok(result) => {
-                    // lines will be the list of tmux sessions
-                    let count = result.lines().count();
-                    let lines: Vec<&str> = result.lines().collect();
-
-                    if count > 0 {
-                        // print the sessions with an index from which to choose (1 based)
-                        result.lines().enumerate().for_each(|(index, line)| {
-                            println!("{}) {}", index + 1, line);
-                        });
-
-                        print!("$ ");
-                        // `print!` doesn't output until we do this
-                        match io::stdout().flush() {
-                            Ok(_result) => (),
-                            Err(error) => panic!("error: {}", error),
+                    let mut sessions = SessionList::parse(result);
+
+                    options.apply(&mut sessions);
+
+                    // the full list, which an empty answer goes back to
+                    let all = sessions.clone();
+
+                    loop {
+                        // lines will be the list of tmux sessions
+                        let lines: Vec<&str> = sessions.names();
+                        let count = lines.len();
+
+                        commands::exit_on_broken_pipe(show_listing(out, sessions.listing()));
+
+                        // the choice an empty answer stands for, if it stands for any
+                        let default_choice = match config::get().default_selection {
+                            DefaultSelection::None => None,
+                            DefaultSelection::First => Some(1).filter(|_first| count > 0),
+                            DefaultSelection::Mru => sessions.most_recent().map(|index| index + 1),
                         };
 
-                        let mut choice = String::new();
-                        let stdin = io::stdin();
+                        let hint = menu_hint(count, default_choice);
 
-                        match stdin.read_line(&mut choice) {
-                            Ok(_result) => (),
-                            Err(error) => panic!("error: {}", error),
+                        if prompt::is_interactive() {
+                            say(out, &term::paint(&hint, Color::Dim));
+                        }
+
+                        let answer = match default_choice {
+                            Some(default_choice) => ask(&format!("$ [{}] ", default_choice)),
+                            None => ask("$ "),
                         };
 
-                        let choice_index: usize = match choice.trim().parse::<usize>() {
-                            Ok(result) => result,
-                            Err(error) => {
-                                println!("error: {}", error);
-                                // return something out of bounds so the `if` below fails
-                                count + 1
+                        let choice = match (answer, default_choice) {
+                            (Some(answer), Some(default_choice)) if answer.is_empty() => {
+                                default_choice.to_string()
+                            }
+                            (Some(answer), _default_choice) => answer,
+                            // the input ran out, so nobody is left to come back to the menu for
+                            (None, _default_choice) if hub => return false,
+                            // stdin was empty from the start (e.g. `< /dev/null`), so there's
+                            // nobody to ask and only the configured default can be a choice
+                            (None, Some(default_choice)) => default_choice.to_string(),
+                            (None, None) => {
+                                report::fail(
+                                    "no input available, pipe in a choice or set default_selection",
+                                    1,
+                                );
                             }
                         };
 
-                        if choice_index > count || choice_index < 1 {
-                            println!("You didn't select an appropriate choice");
-                        } else {
-                            // we need the actual session name associated with the choice the user made
-                            let session = lines[choice_index - 1].to_string();
-                            // attach to the session that was chosen
-                            // tmux attach -t <session>
-                            let tmux_attach = scuttle::App {
-                                command: String::from("tmux"),
-                                args: vec!["attach".to_string(), "-t".to_string(), session],
-                            };
-
-                            match scuttle::run_status(&tmux_attach) {
-                                Ok(_status) => (),
-                                Err(error) => panic!("error: {}", error),
-                            };
+                        if choice == "q" {
+                            return false;
                         }
+
+                        return match parse_selection(&choice, &lines) {
+                            // giving up on naming it is going back to the menu, hub or not
+                            Selection::New => create_session(&lines) || hub,
+                            Selection::Fuzzy(query)
+                                if resolve_selection(&query, &lines).is_none() =>
+                            {
+                                if query.is_empty() {
+                                    // with the whole list showing there's nothing to go back to
+                                    if sessions == all {
+                                        say(
+                                            out,
+                                            &format!(
+                                                "You didn't select an appropriate choice: {}",
+                                                hint
+                                            ),
+                                        );
+                                        return hub;
+                                    }
+
+                                    sessions = all.clone();
+                                    continue;
+                                }
+
+                                let matching: Vec<String> = filter_sessions(&lines, &query)
+                                    .into_iter()
+                                    .map(String::from)
+                                    .collect();
+
+                                if matching.is_empty() {
+                                    say(out, &format!("No sessions match '{}'", query));
+                                } else {
+                                    sessions
+                                        .sessions
+                                        .retain(|session| matching.contains(&session.name));
+                                }
+
+                                // the numbers from here on are for the narrowed down list
+                                continue;
+                            }
+                            Selection::Index(_) | Selection::Fuzzy(_) => {
+                                // we need the actual session name associated with the choice the user made
+                                let session = match resolve_selection(&choice, &lines) {
+                                    Some(session) => session,
+                                    None => {
+                                        say(
+                                            out,
+                                            &format!(
+                                                "You didn't select an appropriate choice: {}",
+                                                hint
+                                            ),
+                                        );
+                                        return hub;
+                                    }
+                                };
+
+                                if !tmux::attach(&session) && !hub {
+                                    process::exit(1);
+                                }
+
+                                // attach only returns once we've detached
+                                hub
+                            }
+                            Selection::Edit(index) => {
+                                edit_session(lines[index]);
+                                true
+                            }
+                            Selection::Rename(index) => {
+                                commands::rename_interactive(lines[index]);
+                                true
+                            }
+                            Selection::Kill(index) => {
+                                commands::exit_on_broken_pipe(kill_session(out, lines[index]));
+                                true
+                            }
+                            Selection::Invalid => {
+                                say(
+                                    out,
+                                    &format!("You didn't select an appropriate choice: {}", hint),
+                                );
+                                hub
+                            }
+                        };
                     }
                 }
-                Err(error) => panic!("error: {}", error),
+                Err(error) => report::fail(
+                    format!("tmux listed sessions that aren't valid UTF-8: {}", error),
+                    1,
+                ),
             }
-            match std::str::from_utf8(&output.stderr) {
-                Ok(result) => println!("{}", result),
-                Err(error) => println!("{}", error),
+        }
+        Err(error) => tmux::fail_to_run(error),
+    }
+}
+
+/// Pick a session like the menu does but print its name instead of attaching
+///
+/// The list and prompts go to stderr so a shell function can capture the name
+/// and decide what to do with it. `n` starts the new session without attaching
+/// and prints its name. Exits with 1 when nothing was picked.
+fn select(options: &ListOptions) {
+    prompt::use_stderr();
+
+    // with no server running there's only `n` to choose
+    let mut sessions = tmux::running_sessions();
+
+    options.apply(&mut sessions);
+
+    let lines = sessions.names();
+
+    commands::exit_on_broken_pipe(show_listing(&mut prompt::output(), sessions.listing()));
+
+    let chosen = loop {
+        let answer = match prompt::ask("$ ") {
+            Some(answer) if !answer.is_empty() && answer != "q" => answer,
+            _ => process::exit(1),
+        };
+
+        match parse_selection(&answer, &lines) {
+            Selection::New => {
+                match fs_utils::prompt_valid_path().map(|path| start_session(&path, &lines)) {
+                    Some(Started::Session(name)) => break Some(name),
+                    // back to picking
+                    Some(Started::GaveUp) => continue,
+                    Some(Started::Nothing) | None => break None,
+                }
+            }
+            Selection::Index(_) | Selection::Fuzzy(_) => match resolve_selection(&answer, &lines) {
+                Some(session) => break Some(session),
+                None => prompt::say("You didn't select an appropriate choice"),
+            },
+            // picking is all --select does
+            Selection::Edit(_) | Selection::Rename(_) | Selection::Kill(_) | Selection::Invalid => {
+                prompt::say("You didn't select an appropriate choice")
+            }
+        }
+    };
+
+    match chosen {
+        Some(name) => println!("{}", name),
+        None => process::exit(1),
+    }
+}
+
+/// Pick from the sessions of every tmux server found and attach through its socket
+///
+/// Each session is listed with the name of its socket, the one `tmux -L`
+/// takes. Sockets nobody answers on are left out.
+fn all_sockets() {
+    let found: Vec<(PathBuf, String)> = tmux::sockets()
+        .into_iter()
+        .filter_map(|socket| tmux::list_sessions_on(&socket).map(|sessions| (socket, sessions)))
+        .flat_map(|(socket, sessions)| {
+            sessions
+                .sessions
+                .into_iter()
+                .map(move |session| (socket.clone(), session.name))
+        })
+        .collect();
+
+    if found.is_empty() {
+        report::fail("no tmux server is running under any socket", 1);
+    }
+
+    let width = found
+        .iter()
+        .map(|(_socket, name)| name.chars().count())
+        .max()
+        .unwrap_or_default();
+
+    let labels: Vec<String> = found
+        .iter()
+        .map(|(socket, name)| {
+            let socket = socket.file_name().unwrap_or_default().to_string_lossy();
+
+            format!("{:<width$}  [{}]", name, socket, width = width)
+        })
+        .collect();
+
+    commands::exit_on_broken_pipe(print_sessions(
+        &mut prompt::output(),
+        &labels,
+        term::width(),
+    ));
+
+    let names: Vec<&str> = found.iter().map(|(_socket, name)| name.as_str()).collect();
+
+    let (socket, name) = loop {
+        let answer = match prompt::ask("$ ") {
+            Some(answer) if !answer.is_empty() && answer != "q" => answer,
+            _ => return,
+        };
+
+        match parse_selection(&answer, &names) {
+            Selection::Index(index) => break &found[index],
+            // the other actions belong to the menu for a single server
+            _ if found.len() == 1 => {
+                println!("You didn't select an appropriate choice: 1 attach, q quit")
             }
+            _ => println!(
+                "You didn't select an appropriate choice: 1-{} attach, q quit",
+                found.len()
+            ),
         }
-        Err(error) => panic!("error: {}", error),
     };
+
+    tmux::use_socket(socket);
+
+    if !tmux::attach(name) {
+        process::exit(1);
+    }
+}
+
+/// Write what `SessionList::listing` found to `out`, or say there's nothing to pick
+///
+/// The menu's own output all goes through the same writer, tests/menu.rs
+/// checks it as a whole against the stand-in tmux from tests/common.
+fn show_listing(out: &mut dyn Write, listing: Listing) -> io::Result<()> {
+    write_listing(out, listing, term::width())
+}
+
+/// `show_listing` for a terminal `width` columns wide, or of unknown width
+fn write_listing(out: &mut dyn Write, listing: Listing, width: Option<usize>) -> io::Result<()> {
+    match listing {
+        Listing::Empty => writeln!(out, "No existing tmux sessions found."),
+        Listing::Sessions(labels) => print_sessions(out, &labels, width),
+    }
+}
+
+/// Write the sessions to `out` with an index from which to choose (1 based)
+///
+/// Lines too wide for `width` are cut short rather than wrapped
+fn print_sessions<T: AsRef<str>>(
+    out: &mut dyn Write,
+    lines: &[T],
+    width: Option<usize>,
+) -> io::Result<()> {
+    for (index, line) in lines.iter().enumerate() {
+        let line = format!("{}) {}", index + 1, line.as_ref());
+
+        match width {
+            Some(width) => writeln!(out, "{}", term::truncate_visible(&line, width))?,
+            None => writeln!(out, "{}", line)?,
+        }
+    }
+
+    Ok(())
+}
+
+/// Kill one session picked at the menu, once the user has confirmed it, saying so to `out`
+fn kill_session(out: &mut dyn Write, session: &str) -> io::Result<()> {
+    if !prompt::confirm_destructive(&format!("Kill {}?", session)) {
+        return writeln!(out, "Nothing was killed.");
+    }
+
+    match tmux::kill_session(session) {
+        Ok(()) => writeln!(out, "Killed {}", session),
+        Err(error) => {
+            report::error(format!("could not kill {}: {}", session, error));
+            Ok(())
+        }
+    }
+}
+
+/// Pick any number of sessions from the list and kill them all at once
+fn interactive_kill(options: &ListOptions) {
+    // with no server running there's nothing to kill
+    let mut sessions = tmux::running_sessions();
+
+    options.apply(&mut sessions);
+
+    let lines = sessions.names();
+
+    if lines.is_empty() {
+        println!("No existing tmux sessions found.");
+        return;
+    }
+
+    commands::exit_on_broken_pipe(print_sessions(
+        &mut prompt::output(),
+        &sessions.labels(),
+        term::width(),
+    ));
+
+    let chosen = loop {
+        let answer = match prompt::ask("Sessions to kill (e.g. 1 3 5): ") {
+            Some(answer) if !answer.is_empty() => answer,
+            _ => return,
+        };
+
+        match prompt::parse_indices(&answer, lines.len()) {
+            Ok(chosen) => break chosen,
+            Err(error) => println!("{}", error),
+        }
+    };
+
+    let chosen: Vec<&str> = chosen.iter().map(|index| lines[*index]).collect();
+
+    println!("This will kill:");
+    chosen.iter().for_each(|name| println!("  {}", name));
+
+    if !prompt::confirm_destructive(&format!("Kill {} sessions?", chosen.len())) {
+        println!("Nothing was killed.");
+        return;
+    }
+
+    for name in chosen {
+        match tmux::kill_session(name) {
+            Ok(()) => println!("Killed {}", name),
+            Err(error) => report::error(format!("could not kill {}: {}", name, error)),
+        }
+    }
+}
+
+/// Walk the user through starting a new session and attach to it
+///
+/// `existing` is the list of sessions already running, used to catch name collisions.
+/// Returns `true` when the user gave up on naming it, and so should be back at the menu
+fn create_session(existing: &[&str]) -> bool {
+    match fs_utils::prompt_valid_path() {
+        Some(path) => create_session_in(&path, existing),
+        None => false,
+    }
+}
+
+/// Create a session for the directory `path` after asking what to call it
+///
+/// Returns `true` when the user gave up on naming it
+fn create_session_in(path: &Path, existing: &[&str]) -> bool {
+    match start_session(path, existing) {
+        Started::Session(name) => {
+            tmux::attach(&name);
+            false
+        }
+        Started::GaveUp => true,
+        Started::Nothing => false,
+    }
+}
+
+/// What came of `start_session`
+enum Started {
+    /// the session to use, which may be one that already existed
+    Session(String),
+    /// no name was settled on, see `NameChoice::Abort`
+    GaveUp,
+    /// nothing was started, because it was refused, failed or the input ran out
+    Nothing,
+}
+
+/// Ask what to call a session for `path` and start it without attaching
+fn start_session(path: &Path, existing: &[&str]) -> Started {
+    let suggested = session_utils::suggest_name_from_path(path);
+
+    let name = match session_utils::resolve_session_name(&suggested, existing) {
+        Some(NameChoice::Create(name)) => name,
+        Some(NameChoice::Attach(name)) => return Started::Session(name),
+        Some(NameChoice::Abort) => return Started::GaveUp,
+        None => return Started::Nothing,
+    };
+
+    if config::get().preview {
+        prompt::say(&format!(
+            "Will run: {}",
+            tmux::command_line(&tmux::new_session_app(&name, path))
+        ));
+
+        if prompt::confirm("Run it?", config::get().confirm_default) != Some(true) {
+            return Started::Nothing;
+        }
+    }
+
+    match tmux::new_session(&name, path) {
+        // the first session starts the server too, make sure it's really there before using it
+        Ok(()) if !tmux::wait_for_session(&name) => {
+            report::error(format!(
+                "session {} was created but tmux isn't listing it, not attaching",
+                name
+            ));
+            Started::Nothing
+        }
+        Ok(()) => Started::Session(name),
+        Err(NewSessionError::Duplicate) => {
+            // something else created it between our collision check and now
+            prompt::say(&format!(
+                "A session named '{}' was created in the meantime.",
+                name
+            ));
+
+            if prompt::confirm("Attach to it?", config::get().confirm_default) == Some(true) {
+                Started::Session(name)
+            } else {
+                Started::Nothing
+            }
+        }
+        Err(NewSessionError::Failed(message)) => {
+            report::error(format!("could not create session {}: {}", name, message));
+            Started::Nothing
+        }
+        Err(NewSessionError::Io(error)) => tmux::fail_to_run(error),
+    }
+}
+
+/// Create a session grouped with `group` and attach to it
+///
+/// Unlike `--clone-layout` the windows aren't copied but shared, the two
+/// sessions are views of the same windows that can each show a different one
+fn group_with(group: &str) {
+    // with no server running there's nothing to group with
+    let sessions = tmux::running_sessions();
+    let existing = sessions.names();
+
+    if !existing.contains(&group) {
+        report::fail(format!("no session named {}", group), 1);
+    }
+
+    let suggested = format!("{}-2", group);
+
+    let name = match session_utils::resolve_session_name(&suggested, &existing) {
+        Some(NameChoice::Create(name)) => name,
+        Some(NameChoice::Attach(name)) => {
+            tmux::attach(&name);
+            return;
+        }
+        Some(NameChoice::Abort) | None => return,
+    };
+
+    match tmux::new_grouped_session(&name, group) {
+        Ok(()) if !tmux::wait_for_session(&name) => report::error(format!(
+            "session {} was created but tmux isn't listing it, not attaching",
+            name
+        )),
+        Ok(()) => {
+            tmux::attach(&name);
+        }
+        Err(NewSessionError::Duplicate) => {
+            report::fail(format!("a session named {} already exists", name), 1);
+        }
+        Err(NewSessionError::Failed(message)) => {
+            report::fail(format!("could not create session {}: {}", name, message), 1);
+        }
+        Err(NewSessionError::Io(error)) => tmux::fail_to_run(error),
+    }
+}
+
+/// Pick a directory under `root` by walking through it and create a session there
+fn browse(root: &str) {
+    let root = match fs_utils::check_dir(root) {
+        Ok(root) => root,
+        Err(full_path) => {
+            fs_utils::explain_not_dir(&full_path);
+            process::exit(1);
+        }
+    };
+
+    if let Some(path) = fs_utils::browse(&root) {
+        // with no server running nothing else exists yet
+        let sessions = tmux::running_sessions();
+
+        create_session_in(&path, &sessions.names());
+    }
+}
+
+/// Kill the session `name` and start it again fresh in the same directory, then attach
+///
+/// A session that isn't running is started in the current directory, unless
+/// `must_exist` says that's an error
+fn replace_session(name: &str, must_exist: bool) {
+    let path = if tmux::session_exists(name) {
+        let path = match tmux::session_path(name) {
+            Ok(path) => PathBuf::from(path),
+            Err(error) => {
+                report::fail(
+                    format!("could not get the directory for {}: {}", name, error),
+                    1,
+                );
+            }
+        };
+
+        let question = format!("Kill {} and start it again in {}?", name, path.display());
+
+        if !prompt::confirm_destructive(&question) {
+            println!("Nothing was killed.");
+            return;
+        }
+
+        // tmux has let go of the name by the time this returns, so it can be used again straight away
+        if let Err(error) = tmux::kill_session(&tmux::exact(name)) {
+            report::fail(format!("could not kill {}: {}", name, error), 1);
+        }
+
+        path
+    } else if must_exist {
+        report::fail(format!("no session named {}", name), 1);
+    } else {
+        if let Err(reason) = session_utils::is_valid_session_name(name) {
+            report::fail(
+                format!("'{}' can't be used: {}", name.escape_debug(), reason),
+                2,
+            );
+        }
+
+        match env::current_dir() {
+            Ok(path) => path,
+            Err(error) => report::fail(
+                format!("could not read the current directory: {}", error),
+                1,
+            ),
+        }
+    };
+
+    match tmux::new_session(name, &path) {
+        Ok(()) if !tmux::wait_for_session(name) => report::error(format!(
+            "session {} was created but tmux isn't listing it, not attaching",
+            name
+        )),
+        Ok(()) => {
+            tmux::attach(name);
+        }
+        Err(NewSessionError::Duplicate) => {
+            report::fail(
+                format!("a session named {} was created again in the meantime", name),
+                1,
+            );
+        }
+        Err(NewSessionError::Failed(message)) => {
+            report::fail(format!("could not create session {}: {}", name, message), 1);
+        }
+        Err(NewSessionError::Io(error)) => tmux::fail_to_run(error),
+    }
+}
+
+/// Create a session for the directory path on the clipboard
+fn from_clipboard() {
+    let contents = match clipboard::read() {
+        Ok(contents) => contents,
+        Err(error) => {
+            report::fail(format!("could not read the clipboard: {}", error), 1);
+        }
+    };
+
+    // file managers copy a `file://` URL rather than a plain path
+    let copied = contents.lines().next().unwrap_or_default().trim();
+    let copied = copied.strip_prefix("file://").unwrap_or(copied);
+
+    if copied.is_empty() {
+        report::fail("the clipboard is empty", 1);
+    }
+
+    let path = match fs_utils::check_dir(copied) {
+        Ok(path) => path,
+        Err(full_path) => {
+            fs_utils::explain_not_dir(&full_path);
+            process::exit(1);
+        }
+    };
+
+    // with no server running nothing else exists yet
+    let sessions = tmux::running_sessions();
+
+    create_session_in(&path, &sessions.names());
+}
+
+/// Open the directory a session was started in with `$EDITOR`
+///
+/// Blocks until the editor exits
+fn edit_session(session: &str) {
+    let editor = match env::var("EDITOR") {
+        Ok(editor) if !editor.trim().is_empty() => editor,
+        _ => {
+            report::error("$EDITOR is not set, don't know which editor to open");
+            return;
+        }
+    };
+
+    let path = match tmux::session_path(session) {
+        Ok(path) => path,
+        Err(error) => {
+            report::error(format!(
+                "could not get the directory for {}: {}",
+                session, error
+            ));
+            return;
+        }
+    };
+
+    // only the editor runs in the session's directory, tm's own stays put
+    if !Path::new(&path).is_dir() {
+        report::error(format!("could not change to {}: no such directory", path));
+        return;
+    }
+
+    // $EDITOR may carry its own arguments, e.g. `code --wait`
+    let mut words = editor.split_whitespace();
+    let command = words.next().unwrap_or_default().to_string();
+    let args: Vec<&str> = words.collect();
+
+    // $EDITOR .
+    match process::Command::new(&command)
+        .args(&args)
+        .arg(".")
+        .current_dir(&path)
+        .status()
+    {
+        Ok(_status) => (),
+        Err(error) => report::error(format!("could not run {}: {}", editor, error)),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LINES: [&str; 3] = ["work", "play", "notes"];
+
+    #[test]
+    fn parse_selection_takes_a_number() {
+        assert_eq!(parse_selection("2", &LINES), Selection::Index(1));
+    }
+
+    #[test]
+    fn parse_selection_ignores_a_trailing_dot() {
+        assert_eq!(parse_selection("2.", &LINES), Selection::Index(1));
+    }
+
+    #[test]
+    fn parse_selection_ignores_surrounding_whitespace() {
+        assert_eq!(parse_selection(" 2 ", &LINES), Selection::Index(1));
+    }
+
+    #[test]
+    fn parse_selection_passes_text_on_as_fuzzy() {
+        assert_eq!(
+            parse_selection("abc", &LINES),
+            Selection::Fuzzy(String::from("abc"))
+        );
+    }
+
+    #[test]
+    fn parse_selection_takes_n_for_a_new_session() {
+        assert_eq!(parse_selection("n", &LINES), Selection::New);
+    }
+
+    #[test]
+    fn parse_selection_refuses_numbers_outside_the_list() {
+        assert_eq!(parse_selection("0", &LINES), Selection::Invalid);
+        assert_eq!(parse_selection("4", &LINES), Selection::Invalid);
+    }
+
+    #[test]
+    fn parse_selection_reads_actions() {
+        assert_eq!(parse_selection("e1", &LINES), Selection::Edit(0));
+        assert_eq!(parse_selection("r 2", &LINES), Selection::Rename(1));
+        assert_eq!(parse_selection("k3.", &LINES), Selection::Kill(2));
+    }
+
+    #[test]
+    fn parse_selection_prefers_a_session_named_like_an_action() {
+        let lines = ["work", "k2", "e1"];
+
+        assert_eq!(parse_selection("k2", &lines), Selection::Index(1));
+        assert_eq!(parse_selection("e1", &lines), Selection::Index(2));
+        assert_eq!(parse_selection("r1", &lines), Selection::Rename(0));
+    }
+
+    #[test]
+    fn parse_selection_keeps_n_and_numbers_for_themselves() {
+        let lines = ["n", "2", "work"];
+
+        assert_eq!(parse_selection("n", &lines), Selection::New);
+        assert_eq!(parse_selection("2", &lines), Selection::Index(1));
+        assert_eq!(parse_selection("3", &lines), Selection::Index(2));
+    }
+
+    #[test]
+    fn filter_sessions_matches_part_of_a_name() {
+        assert_eq!(filter_sessions(&LINES, "o"), ["work", "notes"]);
+        assert_eq!(filter_sessions(&LINES, "lay"), ["play"]);
+    }
+
+    #[test]
+    fn filter_sessions_ignores_case() {
+        assert_eq!(filter_sessions(&LINES, "WoRK"), ["work"]);
+        assert_eq!(filter_sessions(&["Work", "play"], "work"), ["Work"]);
+    }
+
+    #[test]
+    fn filter_sessions_can_match_nothing() {
+        assert!(filter_sessions(&LINES, "zz").is_empty());
+        assert!(filter_sessions(&[], "").is_empty());
+    }
+
+    #[test]
+    fn filter_sessions_keeps_everything_for_an_empty_query() {
+        assert_eq!(filter_sessions(&LINES, ""), LINES);
+    }
+
+    #[test]
+    fn resolve_selection_takes_a_number() {
+        assert_eq!(resolve_selection("3", &LINES).as_deref(), Some("notes"));
+    }
+
+    #[test]
+    fn resolve_selection_takes_an_exact_name() {
+        assert_eq!(resolve_selection("play", &LINES).as_deref(), Some("play"));
+        // even when it's also the start of another name
+        assert_eq!(
+            resolve_selection("web", &["web", "webapp"]).as_deref(),
+            Some("web")
+        );
+    }
+
+    #[test]
+    fn resolve_selection_refuses_what_matches_nothing() {
+        assert_eq!(resolve_selection("zz", &LINES), None);
+        // the start of a name narrows the list down instead
+        assert_eq!(resolve_selection("no", &LINES), None);
+        assert_eq!(resolve_selection("", &["work"]), None);
+        assert_eq!(resolve_selection("k1", &LINES), None);
+    }
+
+    fn listed(listing: Listing, width: Option<usize>) -> String {
+        let mut out = Vec::new();
+
+        write_listing(&mut out, listing, width).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn write_listing_numbers_the_sessions() {
+        let listing = Listing::Sessions(vec![String::from("work"), String::from("play")]);
+
+        assert_eq!(listed(listing, None), "1) work\n2) play\n");
+    }
+
+    #[test]
+    fn write_listing_says_when_there_are_no_sessions() {
+        assert_eq!(
+            listed(Listing::Empty, None),
+            "No existing tmux sessions found.\n"
+        );
+    }
+
+    #[test]
+    fn write_listing_cuts_lines_to_the_width() {
+        let listing = Listing::Sessions(vec![String::from("a-long-session-name")]);
+        let written = listed(listing, Some(10));
+
+        assert!(
+            written.lines().all(|line| term::visible_width(line) <= 10),
+            "{}",
+            written
+        );
+        assert!(written.starts_with("1) a-long"), "{}", written);
+    }
 }
@@ -0,0 +1,126 @@
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+
+/// The project files a name is looked for in, the first one found is used
+const MANIFESTS: [&str; 4] = ["Cargo.toml", "package.json", "pyproject.toml", "README.md"];
+
+/// A table that may carry a `name`
+#[derive(Deserialize)]
+struct Named {
+    name: Option<String>,
+}
+
+/// The parts of a `Cargo.toml` that hold its name
+#[derive(Deserialize)]
+struct CargoManifest {
+    package: Option<Named>,
+}
+
+/// The parts of a `pyproject.toml` that hold its name
+#[derive(Deserialize)]
+struct PyProject {
+    project: Option<Named>,
+    tool: Option<PyTools>,
+}
+
+#[derive(Deserialize)]
+struct PyTools {
+    poetry: Option<Named>,
+}
+
+/// The name the project in `path` gives itself, if it has a manifest that says
+///
+/// Anything that can't be read or parsed is passed over
+pub fn project_name(path: &Path) -> Option<String> {
+    MANIFESTS.iter().find_map(|file| {
+        let contents = fs::read_to_string(path.join(file)).ok()?;
+
+        manifest_name(file, &contents)
+    })
+}
+
+/// The project name declared in `contents`, a manifest called `file`
+///
+/// `[package] name` of a `Cargo.toml`, `name` of a `package.json` without
+/// any `@scope/`, and `[project] name` or `[tool.poetry] name` of a
+/// `pyproject.toml`, and the first `# heading` of a `README.md`. `None` for
+/// other files and when there isn't a name.
+pub fn manifest_name(file: &str, contents: &str) -> Option<String> {
+    let name = match file {
+        "Cargo.toml" => {
+            toml::from_str::<CargoManifest>(contents)
+                .ok()?
+                .package?
+                .name
+        }
+        "package.json" => serde_json::from_str::<Named>(contents)
+            .ok()?
+            .name
+            .map(|name| match name.rsplit_once('/') {
+                Some((_scope, name)) => name.to_string(),
+                None => name,
+            }),
+        "pyproject.toml" => {
+            let pyproject = toml::from_str::<PyProject>(contents).ok()?;
+
+            pyproject
+                .project
+                .and_then(|project| project.name)
+                .or_else(|| pyproject.tool?.poetry?.name)
+        }
+        "README.md" => contents.lines().find_map(|line| {
+            // `# Name #` is a heading too
+            let heading = line.strip_prefix("# ")?;
+
+            Some(heading.trim().trim_end_matches('#').to_string())
+        }),
+        _ => None,
+    }?;
+
+    Some(name.trim().to_string()).filter(|name| !name.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manifest_name_reads_a_cargo_package() {
+        let contents = "[package]\nname = \"tm\"\nversion = \"0.3.0\"\n";
+
+        assert_eq!(manifest_name("Cargo.toml", contents).as_deref(), Some("tm"));
+        assert_eq!(manifest_name("Cargo.toml", "[workspace]\n"), None);
+    }
+
+    #[test]
+    fn manifest_name_reads_package_json_without_the_scope() {
+        assert_eq!(
+            manifest_name("package.json", r#"{"name": "web-app"}"#).as_deref(),
+            Some("web-app")
+        );
+        assert_eq!(
+            manifest_name("package.json", r#"{"name": "@acme/web-app"}"#).as_deref(),
+            Some("web-app")
+        );
+    }
+
+    #[test]
+    fn manifest_name_reads_the_first_readme_heading() {
+        let contents = "Some intro\n\n# My Project #\n\n## Usage\n# Other\n";
+
+        assert_eq!(
+            manifest_name("README.md", contents).as_deref(),
+            Some("My Project")
+        );
+        assert_eq!(manifest_name("README.md", "## Usage\n#hashtag\n"), None);
+    }
+
+    #[test]
+    fn manifest_name_passes_over_what_it_cant_use() {
+        assert_eq!(manifest_name("Cargo.toml", "not toml ["), None);
+        assert_eq!(manifest_name("package.json", r#"{"name": "  "}"#), None);
+        assert_eq!(manifest_name("Makefile", "name = tm"), None);
+    }
+}
@@ -0,0 +1,43 @@
+use std::io;
+use std::io::{IsTerminal, Write};
+
+/// Let the user know something slow has finished, so they can look away meanwhile
+///
+/// Shows a desktop notification with whichever tool is available, notify-send
+/// on Linux or osascript on macOS. Without either the terminal bell is rung,
+/// and when stderr isn't a terminal either nothing happens.
+pub fn done(message: &str) {
+    // display notification "<message>" with title "tm"
+    let script = format!(
+        "display notification \"{}\" with title \"tm\"",
+        message.replace('\\', "\\\\").replace('"', "\\\"")
+    );
+
+    // tried in order, the first one that's installed and works wins
+    let tools = [
+        ("notify-send", vec![String::from("tm"), message.to_string()]),
+        ("osascript", vec![String::from("-e"), script]),
+    ];
+
+    for (command, args) in tools {
+        let app = scuttle::App {
+            command: command.to_string(),
+            args,
+        };
+
+        // not installed, or installed but with nowhere to show it, e.g. no desktop session
+        if let Ok(output) = scuttle::run_output(&app) {
+            if output.status.success() {
+                return;
+            }
+        }
+    }
+
+    if io::stderr().is_terminal() {
+        let mut stderr = io::stderr();
+
+        // a bell that can't be rung isn't worth failing over
+        let _ = write!(stderr, "\x07");
+        let _ = stderr.flush();
+    }
+}
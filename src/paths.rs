@@ -0,0 +1,126 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/// Where `tm` keeps its settings
+///
+/// `$XDG_CONFIG_HOME/tm`, or `~/.config/tm` when that isn't set
+pub fn config_dir() -> Option<PathBuf> {
+    xdg_dir("XDG_CONFIG_HOME", &[".config"])
+}
+
+/// Where `tm` keeps things it remembers between runs
+///
+/// `$XDG_STATE_HOME/tm`, or `~/.local/state/tm` when that isn't set
+pub fn state_dir() -> Option<PathBuf> {
+    xdg_dir("XDG_STATE_HOME", &[".local", "state"])
+}
+
+/// Where tmux puts its sockets when it isn't given one with -S
+///
+/// `$TMUX_TMPDIR`, or `/tmp` when that isn't set, the same as tmux works it out.
+/// tmux itself then uses the `tmux-<uid>` directory inside it
+pub fn tmux_socket_dir() -> PathBuf {
+    env::var_os("TMUX_TMPDIR")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("/tmp"))
+}
+
+/// Create the directory `file` goes in if it isn't there yet and hand `file` back
+pub fn prepare(file: PathBuf) -> io::Result<PathBuf> {
+    if let Some(directory) = file.parent() {
+        fs::create_dir_all(directory)?;
+    }
+
+    Ok(file)
+}
+
+/// `$<var>/tm`, or `~/<fallback>/tm` when the variable isn't usable
+fn xdg_dir(var: &str, fallback: &[&str]) -> Option<PathBuf> {
+    // the spec says relative paths are to be ignored
+    let base = env::var_os(var)
+        .map(PathBuf::from)
+        .filter(|base| base.is_absolute())
+        .or_else(|| {
+            env::var_os("HOME").map(|home| {
+                fallback
+                    .iter()
+                    .fold(PathBuf::from(home), |path, part| path.join(part))
+            })
+        })?;
+
+    Some(base.join("tm"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the environment is shared by every test, so each variable is only
+    // changed by one test and put back at the end
+
+    #[test]
+    fn config_and_state_dirs_follow_xdg() {
+        let config_home = env::var_os("XDG_CONFIG_HOME");
+        let state_home = env::var_os("XDG_STATE_HOME");
+        let home = env::var_os("HOME").map(PathBuf::from);
+
+        env::set_var("XDG_CONFIG_HOME", "/tmp/tm-test-config");
+        env::set_var("XDG_STATE_HOME", "/tmp/tm-test-state");
+
+        assert_eq!(config_dir(), Some(PathBuf::from("/tmp/tm-test-config/tm")));
+        assert_eq!(state_dir(), Some(PathBuf::from("/tmp/tm-test-state/tm")));
+
+        // the spec says relative ones are to be ignored
+        env::set_var("XDG_CONFIG_HOME", "relative/config");
+        env::set_var("XDG_STATE_HOME", "relative/state");
+
+        assert_eq!(
+            config_dir(),
+            home.as_ref().map(|home| home.join(".config").join("tm"))
+        );
+        assert_eq!(
+            state_dir(),
+            home.as_ref()
+                .map(|home| home.join(".local").join("state").join("tm"))
+        );
+
+        env::remove_var("XDG_CONFIG_HOME");
+
+        assert_eq!(
+            config_dir(),
+            home.as_ref().map(|home| home.join(".config").join("tm"))
+        );
+
+        for (var, value) in [
+            ("XDG_CONFIG_HOME", config_home),
+            ("XDG_STATE_HOME", state_home),
+        ] {
+            match value {
+                Some(value) => env::set_var(var, value),
+                None => env::remove_var(var),
+            }
+        }
+    }
+
+    #[test]
+    fn tmux_socket_dir_follows_tmux_tmpdir() {
+        let tmpdir = env::var_os("TMUX_TMPDIR");
+
+        env::set_var("TMUX_TMPDIR", "/run/user/1000");
+        assert_eq!(tmux_socket_dir(), PathBuf::from("/run/user/1000"));
+
+        // tmux ignores it when empty, and so do we
+        env::set_var("TMUX_TMPDIR", "");
+        assert_eq!(tmux_socket_dir(), PathBuf::from("/tmp"));
+
+        env::remove_var("TMUX_TMPDIR");
+        assert_eq!(tmux_socket_dir(), PathBuf::from("/tmp"));
+
+        if let Some(tmpdir) = tmpdir {
+            env::set_var("TMUX_TMPDIR", tmpdir);
+        }
+    }
+}
@@ -0,0 +1,154 @@
+use std::fmt;
+use std::io;
+use std::io::Write;
+use std::process::{Command, ExitStatus, Output, Stdio};
+use std::thread;
+
+/// Why running a command failed
+#[derive(Debug)]
+pub enum ProcessError {
+    /// there's no such command on the PATH
+    NotFound(String),
+    /// the command couldn't be started or talked to
+    Io(String, io::Error),
+}
+
+impl fmt::Display for ProcessError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ProcessError::NotFound(command) => write!(f, "{}: command not found", command),
+            ProcessError::Io(command, error) => write!(f, "{}: {}", command, error),
+        }
+    }
+}
+
+impl ProcessError {
+    fn new(command: &str, error: io::Error) -> Self {
+        match error.kind() {
+            io::ErrorKind::NotFound => ProcessError::NotFound(command.to_string()),
+            _ => ProcessError::Io(command.to_string(), error),
+        }
+    }
+}
+
+/// Run `command` with `input` written to its stdin and collect what it prints to stdout
+///
+/// stderr is left connected to ours so interactive pickers like `fzf` can
+/// still draw on the terminal, which means `Output::stderr` is always empty.
+pub fn run_with_stdin(command: &str, args: &[&str], input: &str) -> Result<Output, ProcessError> {
+    let mut child = Command::new(command)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|error| ProcessError::new(command, error))?;
+
+    // write from another thread, if the command fills its stdout pipe before reading all of
+    // its input we'd otherwise both be stuck waiting on each other
+    let writer = child.stdin.take().map(|mut stdin| {
+        let input = input.to_string();
+
+        // dropping stdin at the end closes it so the command sees the end of its input
+        thread::spawn(move || stdin.write_all(input.as_bytes()))
+    });
+
+    let output = child
+        .wait_with_output()
+        .map_err(|error| ProcessError::new(command, error))?;
+
+    if let Some(writer) = writer {
+        match writer.join() {
+            Ok(Ok(())) => (),
+            // the command is allowed to stop reading early, e.g. `head`
+            Ok(Err(error)) if error.kind() == io::ErrorKind::BrokenPipe => (),
+            Ok(Err(error)) => return Err(ProcessError::new(command, error)),
+            Err(_panic) => {
+                let error = io::Error::other("writing its input panicked");
+
+                return Err(ProcessError::new(command, error));
+            }
+        }
+    }
+
+    Ok(output)
+}
+
+/// Run `command` and collect stdout and stderr as one stream
+///
+/// Both go into the same pipe so the lines keep the order they were printed
+/// in, which is lost when they're read separately like `run_output` does.
+/// The standard library can only join the two with a shell in between, which
+/// is what `sh -c 'exec "$0" "$@" 2>&1'` is for. The shell exits with 127 when
+/// there's no such command.
+// for showing what tmux printed in verbose logs, nothing asks for that yet
+#[allow(dead_code)]
+pub fn run_output_merged(
+    command: &str,
+    args: &[&str],
+) -> Result<(ExitStatus, Vec<u8>), ProcessError> {
+    let output = Command::new("sh")
+        .args(["-c", "exec \"$0\" \"$@\" 2>&1", command])
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .output()
+        .map_err(|error| ProcessError::new("sh", error))?;
+
+    if output.status.code() == Some(127) {
+        return Err(ProcessError::NotFound(command.to_string()));
+    }
+
+    Ok((output.status, output.stdout))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn run_with_stdin_pipes_the_input_through() {
+        let output = run_with_stdin("sort", &[], "work\nnotes\nplay\n").unwrap();
+
+        assert!(output.status.success());
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            "notes\nplay\nwork\n"
+        );
+    }
+
+    #[test]
+    fn run_with_stdin_copes_with_a_command_that_stops_reading() {
+        let input = "line\n".repeat(100_000);
+        let output = run_with_stdin("head", &["-n", "1"], &input).unwrap();
+
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "line\n");
+    }
+
+    #[test]
+    fn run_with_stdin_says_when_there_is_no_such_command() {
+        assert!(matches!(
+            run_with_stdin("tm-no-such-command", &[], ""),
+            Err(ProcessError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn run_output_merged_keeps_both_streams_in_order() {
+        let (status, merged) =
+            run_output_merged("sh", &["-c", "echo one; echo two >&2; echo three"]).unwrap();
+
+        assert!(status.success());
+        assert_eq!(String::from_utf8_lossy(&merged), "one\ntwo\nthree\n");
+    }
+
+    #[test]
+    fn run_output_merged_says_when_there_is_no_such_command() {
+        match run_output_merged("tm-no-such-command", &[]) {
+            Err(ProcessError::NotFound(command)) => assert_eq!(command, "tm-no-such-command"),
+            other => panic!(
+                "expected NotFound, got {:?}",
+                other.map(|(status, _merged)| status)
+            ),
+        }
+    }
+}
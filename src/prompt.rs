@@ -0,0 +1,262 @@
+use std::collections::BTreeSet;
+use std::io;
+use std::io::{IsTerminal, Write};
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use rustyline::error::ReadlineError;
+use rustyline::Editor;
+
+use crate::config::{self, ConfirmDefault};
+use crate::report;
+
+// cleared by `--no-interactive`, after which anything that would ask a question fails instead
+static PROMPTS_ALLOWED: AtomicBool = AtomicBool::new(true);
+
+// set by `--select`, whose stdout is kept for the chosen session's name
+static ON_STDERR: AtomicBool = AtomicBool::new(false);
+
+/// Show prompts and what goes with them on stderr from here on instead of stdout
+pub fn use_stderr() {
+    ON_STDERR.store(true, Ordering::Relaxed);
+}
+
+/// Where prompts and what goes with them are being written, stdout unless `use_stderr` was called
+pub fn output() -> Box<dyn Write> {
+    if ON_STDERR.load(Ordering::Relaxed) {
+        Box::new(io::stderr())
+    } else {
+        Box::new(io::stdout())
+    }
+}
+
+/// Print a line that's part of asking something, where prompts are being shown
+pub fn say(line: &str) {
+    if let Err(error) = writeln!(output(), "{}", line) {
+        report::fail(format!("could not write the prompt: {}", error), 1);
+    }
+}
+
+/// Allow or forbid asking the user anything from here on
+pub fn allow_prompts(allowed: bool) {
+    PROMPTS_ALLOWED.store(allowed, Ordering::Relaxed);
+}
+
+/// Stop with an error instead of asking `prompt` when prompts aren't allowed
+fn refuse_if_forbidden(prompt: &str) {
+    if !PROMPTS_ALLOWED.load(Ordering::Relaxed) {
+        report::fail(
+            format!(
+                "an answer is needed for \"{}\" but --no-interactive was given",
+                prompt.trim().trim_end_matches(':').trim_end()
+            ),
+            1,
+        );
+    }
+}
+
+/// Whether a person is typing the answers, as opposed to a script piping them in
+pub fn is_interactive() -> bool {
+    io::stdin().is_terminal()
+}
+
+/// Print `prompt` when there is somebody to read it
+fn show(prompt: &str) {
+    refuse_if_forbidden(prompt);
+
+    if is_interactive() {
+        let flushed = if ON_STDERR.load(Ordering::Relaxed) {
+            eprint!("{}", prompt);
+            io::stderr().flush()
+        } else {
+            print!("{}", prompt);
+            // `print!` doesn't output until we do this
+            io::stdout().flush()
+        };
+
+        match flushed {
+            Ok(_result) => (),
+            Err(error) => report::fail(format!("could not write the prompt: {}", error), 1),
+        };
+    }
+}
+
+/// Print `prompt` and read a line of input with the surrounding whitespace removed
+///
+/// The prompt is only shown when stdin is a terminal, piped input is read
+/// line by line in the same order without it. Returns `None` once there is
+/// no more input to read. Exits with an error under `--no-interactive`.
+pub fn ask(prompt: &str) -> Option<String> {
+    show(prompt);
+
+    let mut line = String::new();
+
+    match io::stdin().read_line(&mut line) {
+        Ok(0) => None,
+        Ok(_result) => Some(line.trim().to_string()),
+        Err(error) => report::fail(format!("could not read the answer: {}", error), 1),
+    }
+}
+
+/// Like `ask` but with `initial` already typed in for the user to edit
+///
+/// Without a terminal, or if the line editor can't start, this falls back to
+/// a plain prompt where an empty answer keeps `initial`. Returns `None` once
+/// there is no more input to read or the user presses ctrl-c.
+pub fn ask_with_initial(prompt: &str, initial: &str) -> Option<String> {
+    refuse_if_forbidden(prompt);
+
+    if is_interactive() {
+        if let Ok(mut editor) = Editor::<()>::new() {
+            return match editor.readline_with_initial(prompt, (initial, "")) {
+                Ok(line) => Some(line.trim().to_string()),
+                Err(ReadlineError::Eof) | Err(ReadlineError::Interrupted) => None,
+                Err(error) => report::fail(format!("could not read the answer: {}", error), 1),
+            };
+        }
+    }
+
+    let answer = ask(&format!("{}[{}] ", prompt, initial))?;
+
+    if answer.is_empty() {
+        Some(initial.to_string())
+    } else {
+        Some(answer)
+    }
+}
+
+/// Ask a yes/no question, an empty answer is `default`
+///
+/// The choices are shown as `[Y/n]` or `[y/N]` to match. Anything other than
+/// y, yes, n or no is asked again. Returns `None` once there is no more input.
+pub fn confirm(question: &str, default: ConfirmDefault) -> Option<bool> {
+    let prompt = match default {
+        ConfirmDefault::Yes => format!("{} [Y/n] ", question),
+        ConfirmDefault::No => format!("{} [y/N] ", question),
+    };
+
+    loop {
+        match parse_answer(&ask(&prompt)?, default) {
+            Some(answer) => return Some(answer),
+            None => say("Please answer y or n."),
+        }
+    }
+}
+
+/// What `answer` to a yes/no question says, `default` when it's empty
+///
+/// `None` when it's neither yes nor no
+fn parse_answer(answer: &str, default: ConfirmDefault) -> Option<bool> {
+    match answer.trim().to_lowercase().as_str() {
+        "" => Some(default == ConfirmDefault::Yes),
+        "y" | "yes" => Some(true),
+        "n" | "no" => Some(false),
+        _ => None,
+    }
+}
+
+/// Ask a y/N question about something that can't be undone
+///
+/// Only an explicit `y` or `yes` is a yes, whatever `confirm_default` says.
+/// When `confirm_timeout` is configured and no answer arrives in time the
+/// whole program aborts rather than risk a stray keystroke being taken as the
+/// answer later.
+pub fn confirm_destructive(question: &str) -> bool {
+    let prompt = format!("{} [y/N] ", question);
+    let timeout = config::get().confirm_timeout;
+
+    if timeout == 0 {
+        return confirm(question, ConfirmDefault::No) == Some(true);
+    }
+
+    show(&prompt);
+
+    // stdin can't be read with a timeout, so read it on another thread and wait for that instead
+    let (sender, receiver) = mpsc::channel();
+
+    thread::spawn(move || {
+        let mut line = String::new();
+        let read = io::stdin().read_line(&mut line);
+        let _ = sender.send(read.map(|_count| line));
+    });
+
+    match receiver.recv_timeout(Duration::from_secs(timeout)) {
+        Ok(Ok(answer)) => parse_answer(&answer, ConfirmDefault::No) == Some(true),
+        Ok(Err(error)) => report::fail(format!("could not read the answer: {}", error), 1),
+        Err(_timeout) => {
+            say("");
+            say("Timed out, aborting.");
+            process::exit(130);
+        }
+    }
+}
+
+/// Parse space separated 1 based choices like `1 3 5` out of `count` options
+///
+/// Returns the chosen positions (0 based, in order, without repeats) or a
+/// message naming the first number that isn't a valid choice
+pub fn parse_indices(input: &str, count: usize) -> Result<BTreeSet<usize>, String> {
+    input
+        .split_whitespace()
+        .map(|word| match word.parse::<usize>() {
+            Ok(number) if number >= 1 && number <= count => Ok(number - 1),
+            Ok(number) => Err(format!("{} is not between 1 and {}", number, count)),
+            Err(_error) => Err(format!("{} is not a number", word)),
+        })
+        .collect()
+}
+
+/// Show `items` numbered from 1 under `title` and ask for one of them
+///
+/// Asks again until a valid number is entered and returns the position of
+/// the chosen item. Returns `None` for an empty answer or once the input runs out.
+pub fn pick<T: AsRef<str>>(title: &str, items: &[T]) -> Option<usize> {
+    say(title);
+    items
+        .iter()
+        .enumerate()
+        .for_each(|(index, item)| say(&format!("{}) {}", index + 1, item.as_ref())));
+
+    loop {
+        let answer = ask("$ ")?;
+
+        if answer.is_empty() {
+            return None;
+        }
+
+        match answer.parse::<usize>() {
+            Ok(number) if number >= 1 && number <= items.len() => return Some(number - 1),
+            _ => say(&format!("Enter a number between 1 and {}", items.len())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_answer_takes_yes_and_no() {
+        for default in [ConfirmDefault::Yes, ConfirmDefault::No] {
+            assert_eq!(parse_answer("y", default), Some(true));
+            assert_eq!(parse_answer("Yes", default), Some(true));
+            assert_eq!(parse_answer("n", default), Some(false));
+            assert_eq!(parse_answer(" NO ", default), Some(false));
+        }
+    }
+
+    #[test]
+    fn parse_answer_takes_the_default_when_empty() {
+        assert_eq!(parse_answer("", ConfirmDefault::Yes), Some(true));
+        assert_eq!(parse_answer("", ConfirmDefault::No), Some(false));
+    }
+
+    #[test]
+    fn parse_answer_refuses_anything_else() {
+        assert_eq!(parse_answer("yep", ConfirmDefault::Yes), None);
+        assert_eq!(parse_answer("q", ConfirmDefault::No), None);
+    }
+}
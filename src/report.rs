@@ -0,0 +1,88 @@
+use std::fmt;
+use std::panic;
+use std::process;
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+
+use serde::Serialize;
+
+// set by `--error-format=json`, after which errors are written for programs rather than people
+static JSON: AtomicBool = AtomicBool::new(false);
+
+// how many times `-v` was given, the more the more `verbose` says
+static VERBOSITY: AtomicU8 = AtomicU8::new(0);
+
+/// An error as written by `--error-format=json`
+#[derive(Serialize)]
+struct ErrorReport {
+    error: String,
+    /// what `tm` exits with, left out for errors it carries on after
+    #[serde(skip_serializing_if = "Option::is_none")]
+    code: Option<i32>,
+}
+
+/// Write errors as one JSON object per line from here on instead of plain text
+pub fn use_json() {
+    JSON.store(true, Ordering::Relaxed);
+
+    // panics are the errors nobody expected, they still shouldn't break the format
+    panic::set_hook(Box::new(|info| {
+        let message = match info.payload().downcast_ref::<String>() {
+            Some(message) => message.clone(),
+            None => match info.payload().downcast_ref::<&str>() {
+                Some(message) => message.to_string(),
+                None => String::from("unexpected error"),
+            },
+        };
+
+        print(&message, Some(101));
+    }));
+}
+
+/// Whether errors are being written as JSON
+pub fn is_json() -> bool {
+    JSON.load(Ordering::Relaxed)
+}
+
+/// Say `verbose` messages up to `level` from here on
+pub fn set_verbosity(level: u8) {
+    VERBOSITY.store(level, Ordering::Relaxed);
+}
+
+/// Say what `tm` is doing on stderr when `-v` was given at least `level` times
+///
+/// These aren't errors, so they stay plain text under `--error-format=json` too
+pub fn verbose(level: u8, message: impl fmt::Display) {
+    if VERBOSITY.load(Ordering::Relaxed) >= level {
+        eprintln!("tm: {}", message);
+    }
+}
+
+/// Tell the user something went wrong and carry on
+pub fn error(message: impl fmt::Display) {
+    print(&message.to_string(), None);
+}
+
+/// Tell the user something went wrong and exit with `code`
+pub fn fail(message: impl fmt::Display, code: i32) -> ! {
+    print(&message.to_string(), Some(code));
+    process::exit(code);
+}
+
+fn print(message: &str, code: Option<i32>) {
+    // panic messages already say they're an error
+    let message = message.strip_prefix("error: ").unwrap_or(message);
+
+    if JSON.load(Ordering::Relaxed) {
+        let report = ErrorReport {
+            error: message.to_string(),
+            code,
+        };
+
+        match serde_json::to_string(&report) {
+            Ok(json) => eprintln!("{}", json),
+            Err(_error) => eprintln!("error: {}", message),
+        }
+    } else {
+        eprintln!("error: {}", message);
+    }
+}
@@ -0,0 +1,667 @@
+use std::collections::BTreeSet;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use regex::Regex;
+
+use crate::config;
+use crate::manifest;
+use crate::prompt;
+
+/// What to do once the name for a new session has been settled
+#[derive(Debug, PartialEq, Eq)]
+pub enum NameChoice {
+    /// create a session with this name
+    Create(String),
+    /// a session with this name already exists, attach to it instead
+    Attach(String),
+    /// the user couldn't settle on anything, give up on creating a session
+    Abort,
+}
+
+/// How many answers that aren't one of the choices are put up with before giving up
+const ATTEMPTS: usize = 3;
+
+/// Suggest a session name from the directory the session will start in
+///
+/// The name is built from the `name_template` config, unless `name_from_manifest`
+/// is on and the directory has a project manifest that declares a name
+pub fn suggest_name_from_path(path: &Path) -> String {
+    if config::get().name_from_manifest {
+        if let Some(name) = manifest::project_name(path) {
+            return sanitize_with_notice(&name);
+        }
+    }
+
+    let git_root = path
+        .ancestors()
+        .find(|ancestor| ancestor.join(".git").exists());
+
+    sanitize_with_notice(&apply_name_template(
+        &config::get().name_template,
+        path,
+        git_root,
+    ))
+}
+
+/// Fill in `template` for a session starting in `path`
+///
+/// `{dir}` is the name of `path`, `{parent}` the name of the directory it's
+/// in, and `{git_root}` the name of the repository `path` is in (or `{dir}`
+/// outside of one). If nothing is left the name of `path` is used on its own.
+pub fn apply_name_template(template: &str, path: &Path, git_root: Option<&Path>) -> String {
+    let dir = dir_name(path);
+    let parent = path.parent().map(dir_name).unwrap_or_default();
+    let git_root = git_root.map(dir_name).unwrap_or_else(|| dir.clone());
+
+    let name = template
+        .replace("{dir}", &dir)
+        .replace("{parent}", &parent)
+        .replace("{git_root}", &git_root);
+
+    if name.trim().is_empty() {
+        dir
+    } else {
+        name
+    }
+}
+
+/// The last component of `path` as a name
+fn dir_name(path: &Path) -> String {
+    match path.file_name() {
+        Some(name) => name.to_string_lossy().into_owned(),
+        // only the root has no name of its own
+        None => String::from("root"),
+    }
+}
+
+/// Replace the characters tmux treats specially in session names with `_`
+///
+/// `.` and `:` separate windows and panes in a target, so a session named
+/// with them can't be picked out by name later. Whitespace works as far as
+/// tmux goes but has to be quoted everywhere the name is typed, so it goes too.
+pub fn sanitize_session_name(raw: &str) -> String {
+    raw.chars()
+        .map(|c| {
+            if c == '.' || c == ':' || c.is_whitespace() {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect()
+}
+
+/// `sanitize_session_name`, saying so when that changed anything
+fn sanitize_with_notice(raw: &str) -> String {
+    let name = sanitize_session_name(raw);
+
+    if name != raw {
+        // escaped so a stray control character doesn't mess up the terminal
+        prompt::say(&format!(
+            "Using '{}' for '{}', session names can't contain ., : or whitespace.",
+            name,
+            raw.escape_debug()
+        ));
+    }
+
+    name
+}
+
+// `name_policy` compiled, the first time a name is checked against it
+static NAME_POLICY: OnceLock<Option<Regex>> = OnceLock::new();
+
+/// The `name_policy` regex names have to match, `None` when there isn't one
+fn name_policy() -> Option<&'static Regex> {
+    NAME_POLICY
+        .get_or_init(|| {
+            let policy = &config::get().name_policy;
+
+            // `config::load` has already refused a pattern that doesn't compile
+            Some(policy)
+                .filter(|policy| !policy.is_empty())
+                .and_then(|policy| Regex::new(policy).ok())
+        })
+        .as_ref()
+}
+
+/// Check a session name typed in by the user
+///
+/// Control characters would garble the status bar, and `.`, `:` and
+/// whitespace stop the session from being picked out by name. On top of
+/// tmux's rules the name has to match `name_policy` when one is set. The
+/// error says what's wrong.
+pub fn is_valid_session_name(name: &str) -> Result<(), String> {
+    if name.trim().is_empty() {
+        return Err(String::from("a session needs a name"));
+    }
+
+    check_control(name)?;
+
+    // after control characters, so a tab is still called a tab
+    if name.chars().any(char::is_whitespace) {
+        return Err(String::from("it contains whitespace, use _ or - instead"));
+    }
+
+    if let Some(separator) = name.chars().find(|c| *c == '.' || *c == ':') {
+        return Err(format!(
+            "it contains '{}', which tmux uses to separate windows and panes",
+            separator
+        ));
+    }
+
+    check_policy(name, name_policy())
+}
+
+/// Refuse a name with a control character in it, naming the character
+///
+/// Typed names are checked with this before `sanitize_session_name`, which
+/// would otherwise quietly turn a pasted tab or newline into `_`.
+fn check_control(name: &str) -> Result<(), String> {
+    let control = match name.chars().find(|c| c.is_control()) {
+        Some(control) => control,
+        None => return Ok(()),
+    };
+
+    let described = match control {
+        '\t' => String::from("a tab"),
+        '\n' => String::from("a newline"),
+        '\r' => String::from("a carriage return"),
+        '\x1b' => String::from("an escape character"),
+        other => format!("the control character U+{:04X}", other as u32),
+    };
+
+    Err(format!("it contains {}", described))
+}
+
+/// `answer` as a session name, sanitized but only once it has no control characters
+fn typed_name(answer: &str) -> Result<String, String> {
+    check_control(answer)?;
+
+    let name = sanitize_with_notice(answer);
+
+    is_valid_session_name(&name).map(|()| name)
+}
+
+/// Whether `name` matches `policy`, which every name does when there's no policy
+fn check_policy(name: &str, policy: Option<&Regex>) -> Result<(), String> {
+    match policy {
+        Some(policy) if !policy.is_match(name) => Err(format!(
+            "it doesn't match the name_policy {}",
+            policy.as_str()
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Ask `prompt` until a usable session name is entered, an empty answer is `default`
+///
+/// Returns `None` if the input runs out
+fn ask_name(
+    prompt: &str,
+    default: &str,
+    ask: &mut dyn FnMut(&str) -> Option<String>,
+) -> Option<String> {
+    loop {
+        let answer = ask(prompt)?;
+        let answer = if answer.is_empty() {
+            default.to_string()
+        } else {
+            answer
+        };
+
+        match typed_name(&answer) {
+            Ok(name) => return fit_length(name),
+            // escaped so whatever was pasted doesn't mess up the terminal again
+            Err(reason) => prompt::say(&format!(
+                "'{}' can't be used: {}",
+                answer.escape_debug(),
+                reason
+            )),
+        }
+    }
+}
+
+/// Ask for the name of a new session, offering `suggested` as the default
+///
+/// If the name is already taken the user can attach to that session or pick
+/// another name, after `ATTEMPTS` answers that are neither it gives up with
+/// `Abort`. Returns `None` if the input runs out.
+pub fn resolve_session_name(suggested: &str, existing: &[&str]) -> Option<NameChoice> {
+    resolve_session_name_with(suggested, existing, &mut prompt::ask)
+}
+
+/// `resolve_session_name` with the answers coming from `ask` rather than the user
+fn resolve_session_name_with(
+    suggested: &str,
+    existing: &[&str],
+    ask: &mut dyn FnMut(&str) -> Option<String>,
+) -> Option<NameChoice> {
+    let mut name = ask_name(&format!("Session name [{}]: ", suggested), suggested, ask)?;
+    let mut invalid = 0;
+
+    while existing.contains(&name.as_str()) {
+        prompt::say(&format!("A session named '{}' already exists.", name));
+
+        match ask("(a)ttach to it or (c)hoose another name? ")?.as_str() {
+            "a" => return Some(NameChoice::Attach(name)),
+            "c" => name = ask_name("Session name: ", &name, ask)?,
+            _ if invalid + 1 >= ATTEMPTS => {
+                prompt::say("Invalid choice, giving up on the new session.");
+                return Some(NameChoice::Abort);
+            }
+            _ => {
+                invalid += 1;
+                prompt::say("Invalid choice.");
+            }
+        }
+    }
+
+    Some(NameChoice::Create(name))
+}
+
+/// Ask for a new name for the session `current`, starting from the current name
+///
+/// Names already taken by one of the `existing` sessions are refused and asked
+/// for again. Returns `None` if the input runs out or the name isn't changed.
+pub fn rename_session_name(current: &str, existing: &[&str]) -> Option<String> {
+    loop {
+        let answer = prompt::ask_with_initial("New name: ", current)?;
+
+        if answer.is_empty() || answer == current {
+            return None;
+        }
+
+        let name = match typed_name(&answer) {
+            Ok(name) => fit_length(name)?,
+            Err(reason) => {
+                prompt::say(&format!(
+                    "'{}' can't be used: {}",
+                    answer.escape_debug(),
+                    reason
+                ));
+                continue;
+            }
+        };
+
+        if existing.contains(&name.as_str()) {
+            prompt::say(&format!("A session named '{}' already exists.", name));
+        } else {
+            return Some(name);
+        }
+    }
+}
+
+/// Work out an order to apply several `(from, to)` renames in without clashes
+///
+/// `existing` is every session running now, renames for ones that keep their
+/// name are ignored. The new names must all differ from each other and from
+/// the sessions that aren't being renamed. When renames go round in a cycle
+/// (`a` to `b` and `b` to `a`) one of them goes through a temporary name first.
+pub fn plan_renames(
+    renames: &[(String, String)],
+    existing: &[&str],
+) -> Result<Vec<(String, String)>, String> {
+    let mut pending: Vec<(String, String)> = renames
+        .iter()
+        .filter(|(from, to)| from != to)
+        .cloned()
+        .collect();
+
+    let mut targets: BTreeSet<String> = BTreeSet::new();
+
+    for (_from, to) in &pending {
+        if !targets.insert(to.clone()) {
+            return Err(format!("more than one session would be named '{}'", to));
+        }
+    }
+
+    for name in existing {
+        let renamed = pending.iter().any(|(from, _to)| from == name);
+
+        if !renamed && targets.contains(*name) {
+            return Err(format!(
+                "'{}' is already taken by a session that keeps its name",
+                name
+            ));
+        }
+    }
+
+    // the names in use at each step, as the renames are applied one by one
+    let mut taken: BTreeSet<String> = existing.iter().map(|name| name.to_string()).collect();
+    let mut planned = Vec::new();
+
+    while !pending.is_empty() {
+        match pending.iter().position(|(_from, to)| !taken.contains(to)) {
+            Some(next) => {
+                let (from, to) = pending.remove(next);
+
+                taken.remove(&from);
+                taken.insert(to.clone());
+                planned.push((from, to));
+            }
+            None => {
+                // every target is held by another session waiting to move, so the
+                // first one steps aside to a name nobody has
+                let (from, to) = pending.remove(0);
+                let mut temporary = format!("{}-renaming", from);
+
+                while taken.contains(&temporary) || targets.contains(&temporary) {
+                    temporary.push('_');
+                }
+
+                taken.remove(&from);
+                taken.insert(temporary.clone());
+                planned.push((from, temporary.clone()));
+                pending.push((temporary, to));
+            }
+        }
+    }
+
+    Ok(planned)
+}
+
+/// Offer to shorten `name` if it's longer than `max_name_length` allows
+///
+/// Returns `None` if the input runs out
+fn fit_length(name: String) -> Option<String> {
+    let max = config::get().max_name_length;
+
+    if max == 0 || name.chars().count() <= max {
+        return Some(name);
+    }
+
+    let truncated = truncate_name(&name, max);
+
+    prompt::say(&format!("'{}' is longer than {} characters.", name, max));
+
+    let question = format!("Shorten it to '{}'?", truncated);
+
+    if prompt::confirm(&question, config::get().confirm_default)? {
+        Some(truncated)
+    } else {
+        Some(name)
+    }
+}
+
+/// Cut `name` down to at most `max` characters
+///
+/// A separator left dangling at the end of what's kept (`-`, `_`, or
+/// whitespace) is dropped too, so `my-long-project` becomes `my-long` rather
+/// than `my-long-`
+pub fn truncate_name(name: &str, max: usize) -> String {
+    let truncated: String = name.chars().take(max).collect();
+    let trimmed = truncated.trim_end_matches(|c: char| c == '-' || c == '_' || c.is_whitespace());
+
+    if trimmed.is_empty() {
+        truncated
+    } else {
+        trimmed.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn apply_name_template_fills_in_dir_and_parent() {
+        let path = Path::new("/home/me/src/tm");
+
+        assert_eq!(apply_name_template("{dir}", path, None), "tm");
+        assert_eq!(apply_name_template("{parent}-{dir}", path, None), "src-tm");
+    }
+
+    #[test]
+    fn apply_name_template_fills_in_the_git_root() {
+        let path = Path::new("/home/me/src/tm/src/bin");
+        let root = Path::new("/home/me/src/tm");
+
+        assert_eq!(
+            apply_name_template("{git_root}/{dir}", path, Some(root)),
+            "tm/bin"
+        );
+        // outside of a repository it's the directory itself
+        assert_eq!(apply_name_template("{git_root}", path, None), "bin");
+    }
+
+    #[test]
+    fn apply_name_template_falls_back_to_the_directory() {
+        assert_eq!(apply_name_template("  ", Path::new("/src/tm"), None), "tm");
+        assert_eq!(apply_name_template("{dir}", Path::new("/"), None), "root");
+    }
+
+    #[test]
+    fn suggest_name_from_path_sanitizes_the_result() {
+        let path = env::temp_dir().join(format!("tm-test-{}-my.app v2", std::process::id()));
+
+        fs::create_dir_all(&path).unwrap();
+        let suggested = suggest_name_from_path(&path);
+        fs::remove_dir_all(&path).unwrap();
+
+        assert!(suggested.ends_with("-my_app_v2"), "{}", suggested);
+        assert_eq!(is_valid_session_name(&suggested), Ok(()));
+    }
+
+    #[test]
+    fn sanitize_session_name_replaces_what_tmux_rejects() {
+        assert_eq!(sanitize_session_name("my.app"), "my_app");
+        assert_eq!(sanitize_session_name("v1.2"), "v1_2");
+        assert_eq!(sanitize_session_name("host:8080"), "host_8080");
+        assert_eq!(sanitize_session_name("my app"), "my_app");
+        assert_eq!(sanitize_session_name("work"), "work");
+    }
+
+    #[test]
+    fn is_valid_session_name_names_the_control_character() {
+        assert_eq!(
+            is_valid_session_name("a\tb"),
+            Err(String::from("it contains a tab"))
+        );
+        assert_eq!(
+            is_valid_session_name("work\nplay"),
+            Err(String::from("it contains a newline"))
+        );
+        assert_eq!(
+            is_valid_session_name("\x1b[31mred"),
+            Err(String::from("it contains an escape character"))
+        );
+        assert_eq!(
+            is_valid_session_name("a\x07"),
+            Err(String::from("it contains the control character U+0007"))
+        );
+    }
+
+    #[test]
+    fn is_valid_session_name_refuses_dots_colons_and_spaces() {
+        assert!(is_valid_session_name("my.app").is_err());
+        assert!(is_valid_session_name("host:8080").is_err());
+        assert!(is_valid_session_name("a b").is_err());
+        assert!(is_valid_session_name(" work").is_err());
+        assert!(is_valid_session_name("my_app").is_ok());
+    }
+
+    #[test]
+    fn typed_name_refuses_a_tab_rather_than_replacing_it() {
+        assert_eq!(typed_name("a\tb"), Err(String::from("it contains a tab")));
+        assert_eq!(typed_name("my app"), Ok(String::from("my_app")));
+    }
+
+    /// Answers `answers` in order and counts how many were asked for
+    fn answering<'a>(
+        answers: &'a [&str],
+        asked: &'a mut usize,
+    ) -> impl FnMut(&str) -> Option<String> + 'a {
+        move |_prompt| {
+            let answer = answers.get(*asked).map(|answer| answer.to_string());
+
+            *asked += 1;
+            answer
+        }
+    }
+
+    #[test]
+    fn resolve_session_name_gives_up_on_repeated_invalid_answers() {
+        let mut asked = 0;
+        let choice = resolve_session_name_with(
+            "work",
+            &["work"],
+            &mut answering(&["", "x", "y", "z", "a"], &mut asked),
+        );
+
+        assert_eq!(choice, Some(NameChoice::Abort));
+        // the name and then ATTEMPTS wrong answers, the last one never gets read
+        assert_eq!(asked, 1 + ATTEMPTS);
+    }
+
+    #[test]
+    fn resolve_session_name_still_takes_a_choice_after_an_invalid_answer() {
+        let mut asked = 0;
+        let choice = resolve_session_name_with(
+            "work",
+            &["work"],
+            &mut answering(&["", "x", "a"], &mut asked),
+        );
+
+        assert_eq!(choice, Some(NameChoice::Attach(String::from("work"))));
+    }
+
+    #[test]
+    fn resolve_session_name_asks_again_for_another_name() {
+        let mut asked = 0;
+        let choice = resolve_session_name_with(
+            "work",
+            &["work"],
+            &mut answering(&["", "c", "play"], &mut asked),
+        );
+
+        assert_eq!(choice, Some(NameChoice::Create(String::from("play"))));
+    }
+
+    #[test]
+    fn resolve_session_name_asks_again_after_a_name_with_a_tab() {
+        let mut asked = 0;
+        let choice = resolve_session_name_with(
+            "work",
+            &[],
+            &mut answering(&["my\tapp", "my-app"], &mut asked),
+        );
+
+        assert_eq!(choice, Some(NameChoice::Create(String::from("my-app"))));
+        assert_eq!(asked, 2);
+    }
+
+    #[test]
+    fn resolve_session_name_stops_when_the_input_runs_out() {
+        let mut asked = 0;
+        let choice =
+            resolve_session_name_with("work", &["work"], &mut answering(&[""], &mut asked));
+
+        assert_eq!(choice, None);
+    }
+
+    fn renames(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(from, to)| (from.to_string(), to.to_string()))
+            .collect()
+    }
+
+    /// The sessions left after applying `plan` to `existing` one step at a time,
+    /// failing if any step would rename onto a name still in use
+    fn apply(plan: &[(String, String)], existing: &[&str]) -> BTreeSet<String> {
+        let mut names: BTreeSet<String> = existing.iter().map(|name| name.to_string()).collect();
+
+        for (from, to) in plan {
+            assert!(names.remove(from), "{} isn't there to rename", from);
+            assert!(names.insert(to.clone()), "{} is still taken", to);
+        }
+
+        names
+    }
+
+    fn names(names: &[&str]) -> BTreeSet<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn plan_renames_swaps_two_sessions_through_a_temporary_name() {
+        let existing = ["a", "b"];
+        let plan = plan_renames(&renames(&[("a", "b"), ("b", "a")]), &existing).unwrap();
+
+        assert_eq!(plan.len(), 3);
+        assert_eq!(apply(&plan, &existing), names(&["a", "b"]));
+        assert_eq!(plan[0], (String::from("a"), String::from("a-renaming")));
+    }
+
+    #[test]
+    fn plan_renames_goes_round_a_three_cycle() {
+        let existing = ["a", "b", "c", "d"];
+        let plan =
+            plan_renames(&renames(&[("a", "b"), ("b", "c"), ("c", "a")]), &existing).unwrap();
+
+        assert_eq!(plan.len(), 4);
+        assert_eq!(apply(&plan, &existing), names(&["a", "b", "c", "d"]));
+    }
+
+    #[test]
+    fn plan_renames_orders_a_chain_without_a_temporary_name() {
+        let existing = ["a", "b"];
+        let plan = plan_renames(&renames(&[("a", "b"), ("b", "c")]), &existing).unwrap();
+
+        assert_eq!(plan, renames(&[("b", "c"), ("a", "b")]));
+    }
+
+    #[test]
+    fn plan_renames_refuses_a_name_kept_by_another_session() {
+        let result = plan_renames(&renames(&[("a", "c")]), &["a", "b", "c"]);
+
+        assert_eq!(
+            result,
+            Err(String::from(
+                "'c' is already taken by a session that keeps its name"
+            ))
+        );
+    }
+
+    #[test]
+    fn plan_renames_refuses_two_sessions_getting_the_same_name() {
+        assert!(plan_renames(&renames(&[("a", "c"), ("b", "c")]), &["a", "b"]).is_err());
+    }
+
+    #[test]
+    fn plan_renames_leaves_out_sessions_keeping_their_name() {
+        let plan = plan_renames(&renames(&[("a", "a"), ("b", "b")]), &["a", "b"]).unwrap();
+
+        assert!(plan.is_empty());
+    }
+
+    #[test]
+    fn check_policy_accepts_matching_names() {
+        let policy = Regex::new("^[a-z][a-z0-9-]*$").unwrap();
+
+        assert_eq!(check_policy("web-2", Some(&policy)), Ok(()));
+        assert_eq!(check_policy("api", Some(&policy)), Ok(()));
+    }
+
+    #[test]
+    fn check_policy_rejects_other_names() {
+        let policy = Regex::new("^[a-z][a-z0-9-]*$").unwrap();
+
+        assert_eq!(
+            check_policy("Web", Some(&policy)),
+            Err(String::from(
+                "it doesn't match the name_policy ^[a-z][a-z0-9-]*$"
+            ))
+        );
+        assert!(check_policy("2web", Some(&policy)).is_err());
+    }
+
+    #[test]
+    fn check_policy_accepts_anything_without_a_policy() {
+        assert_eq!(check_policy("Whatever_2", None), Ok(()));
+    }
+}
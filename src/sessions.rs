@@ -0,0 +1,406 @@
+use std::cmp::Reverse;
+use std::str::FromStr;
+
+use regex::Regex;
+use serde::Serialize;
+
+/// The `-F` format `SessionList::parse` understands, one session per line
+pub const LIST_FORMAT: &str = "#{session_name}\t#{session_windows}\t#{session_attached}\t#{session_last_attached}\t#{session_created}\t#{@note}";
+
+/// The `-F` format every tmux with `-F` understands, just the session names
+pub const NAME_FORMAT: &str = "#S";
+
+/// A single tmux session as reported by `tmux ls`
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Session {
+    pub name: String,
+    /// number of windows in the session
+    pub windows: usize,
+    /// number of clients attached to the session
+    pub attached: usize,
+    /// unix time the session was last attached, 0 if it never was
+    pub last_attached: u64,
+    /// unix time the session was created
+    pub created: u64,
+    /// the session's `@note` user option, empty when it has none
+    pub note: String,
+}
+
+/// The `-F` format `parse_windows` understands, one window per line
+pub const WINDOW_FORMAT: &str =
+    "#{session_name}\t#{window_index}\t#{window_name}\t#{pane_current_path}";
+
+/// A window in some session as reported by `tmux list-windows`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Window {
+    /// the session the window belongs to
+    pub session: String,
+    pub index: usize,
+    pub name: String,
+    /// working directory of the window's active pane
+    pub path: String,
+}
+
+/// Parse the output of `tmux list-windows -F WINDOW_FORMAT`
+pub fn parse_windows(output: &str) -> Vec<Window> {
+    output
+        .lines()
+        .filter_map(|line| {
+            // the session name goes first so split from the right in case it contains a tab
+            let mut fields = line.rsplitn(4, '\t');
+            let path = fields.next()?;
+            let name = fields.next()?;
+            let index = fields.next()?.parse().ok()?;
+            let session = fields.next()?;
+
+            Some(Window {
+                session: session.to_string(),
+                index,
+                name: name.to_string(),
+                path: path.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// The `-F` format `parse_panes` understands, one pane per line
+pub const PANE_FORMAT: &str =
+    "#{session_name}\t#{window_index}\t#{pane_index}\t#{pane_current_command}";
+
+/// A pane in some window as reported by `tmux list-panes`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Pane {
+    /// the session the pane's window belongs to
+    pub session: String,
+    /// index of the pane's window
+    pub window: usize,
+    pub index: usize,
+    /// what's running in the pane, e.g. `zsh` or `vim`
+    pub command: String,
+}
+
+/// Parse the output of `tmux list-panes -F PANE_FORMAT`
+pub fn parse_panes(output: &str) -> Vec<Pane> {
+    output
+        .lines()
+        .filter_map(|line| {
+            // the session name goes first so split from the right in case it contains a tab
+            let mut fields = line.rsplitn(4, '\t');
+            let command = fields.next()?;
+            let index = fields.next()?.parse().ok()?;
+            let window = fields.next()?.parse().ok()?;
+            let session = fields.next()?;
+
+            Some(Pane {
+                session: session.to_string(),
+                window,
+                index,
+                command: command.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// The `-F` format `parse_clients` understands, one client per line
+pub const CLIENT_FORMAT: &str = "#{client_name}\t#{client_termname}";
+
+/// A client attached to a session as reported by `tmux list-clients`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Client {
+    /// usually the tty it's on, e.g. `/dev/pts/3`
+    pub name: String,
+    /// the `TERM` it was started with, e.g. `xterm-256color`
+    pub terminal: String,
+}
+
+/// Parse the output of `tmux list-clients -F CLIENT_FORMAT`
+pub fn parse_clients(output: &str) -> Vec<Client> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (name, terminal) = line.split_once('\t')?;
+
+            Some(Client {
+                name: name.to_string(),
+                terminal: terminal.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// How to order the session list
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Sort {
+    /// alphabetically by name
+    Name,
+    /// most recently attached first
+    Mru,
+    /// most windows first
+    Windows,
+}
+
+impl FromStr for Sort {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "name" => Ok(Sort::Name),
+            "mru" => Ok(Sort::Mru),
+            "windows" => Ok(Sort::Windows),
+            _ => Err(format!("unknown sort order '{}'", value)),
+        }
+    }
+}
+
+/// What there is to show of a `SessionList`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Listing {
+    /// no sessions, so only a new one can be picked
+    Empty,
+    /// the labels of the sessions, in display order
+    Sessions(Vec<String>),
+}
+
+/// The sessions tmux knows about, in display order
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SessionList {
+    pub sessions: Vec<Session>,
+}
+
+impl SessionList {
+    /// Parse the output of `tmux ls -F LIST_FORMAT`
+    ///
+    /// Lines that don't have all the fields are taken to be just a name, so
+    /// plain `tmux ls -F "#S"` output parses too
+    pub fn parse(output: &str) -> Self {
+        let sessions = output
+            .lines()
+            // a blank line isn't a session, however it got there
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                // the name goes first and is the only field that could itself contain a tab
+                let mut fields = line.rsplitn(6, '\t');
+                let note = fields.next();
+                let created = fields.next();
+                let last_attached = fields.next();
+                let attached = fields.next();
+                let windows = fields.next();
+
+                match fields.next() {
+                    Some(name) => Session {
+                        name: name.to_string(),
+                        windows: number(windows),
+                        attached: number(attached),
+                        last_attached: number(last_attached),
+                        created: number(created),
+                        note: note.unwrap_or_default().to_string(),
+                    },
+                    None => Session {
+                        name: line.to_string(),
+                        windows: 0,
+                        attached: 0,
+                        last_attached: 0,
+                        created: 0,
+                        note: String::new(),
+                    },
+                }
+            })
+            .collect();
+
+        SessionList { sessions }
+    }
+
+    /// Reorder the sessions, ties are broken by name so the order is always the same
+    pub fn sort(&mut self, sort: Sort) {
+        match sort {
+            Sort::Name => self.sessions.sort_by(|a, b| a.name.cmp(&b.name)),
+            Sort::Mru => self.sessions.sort_by(|a, b| {
+                (Reverse(a.last_attached), &a.name).cmp(&(Reverse(b.last_attached), &b.name))
+            }),
+            Sort::Windows => self
+                .sessions
+                .sort_by(|a, b| (Reverse(a.windows), &a.name).cmp(&(Reverse(b.windows), &b.name))),
+        }
+    }
+
+    /// Keep only the sessions whose name matches `pattern`
+    pub fn grep(&mut self, pattern: &Regex) {
+        self.sessions
+            .retain(|session| pattern.is_match(&session.name));
+    }
+
+    /// Keep only the sessions whose name starts with `prefix`
+    pub fn retain_prefix(&mut self, prefix: &str) {
+        self.sessions
+            .retain(|session| session.name.starts_with(prefix));
+    }
+
+    /// Keep only the sessions that have a client attached
+    pub fn retain_attached(&mut self) {
+        self.sessions.retain(|session| session.attached > 0);
+    }
+
+    /// Position of the most recently attached session, `None` if none ever were
+    pub fn most_recent(&self) -> Option<usize> {
+        self.sessions
+            .iter()
+            .enumerate()
+            .filter(|(_index, session)| session.last_attached > 0)
+            // the earliest listed wins a tie
+            .min_by_key(|(index, session)| (Reverse(session.last_attached), *index))
+            .map(|(index, _session)| index)
+    }
+
+    /// The session names in display order, with their notes lined up after them
+    pub fn labels(&self) -> Vec<String> {
+        let width = self
+            .sessions
+            .iter()
+            .map(|session| session.name.chars().count())
+            .max()
+            .unwrap_or_default();
+
+        self.sessions
+            .iter()
+            .map(|session| {
+                if session.note.is_empty() {
+                    session.name.clone()
+                } else {
+                    format!("{:<width$}  {}", session.name, session.note, width = width)
+                }
+            })
+            .collect()
+    }
+
+    /// What the list shows, `Empty` when no session is left to show
+    pub fn listing(&self) -> Listing {
+        if self.sessions.is_empty() {
+            Listing::Empty
+        } else {
+            Listing::Sessions(self.labels())
+        }
+    }
+
+    /// The session names in display order
+    pub fn names(&self) -> Vec<&str> {
+        self.sessions
+            .iter()
+            .map(|session| session.name.as_str())
+            .collect()
+    }
+}
+
+/// Parse a numeric format field, tmux leaves some of them empty (e.g. never attached)
+fn number<T: FromStr + Default>(field: Option<&str>) -> T {
+    field
+        .and_then(|field| field.parse().ok())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(name: &str, windows: usize, last_attached: u64) -> Session {
+        Session {
+            name: name.to_string(),
+            windows,
+            attached: 0,
+            last_attached,
+            created: 0,
+            note: String::new(),
+        }
+    }
+
+    fn list(sessions: Vec<Session>) -> SessionList {
+        SessionList { sessions }
+    }
+
+    #[test]
+    fn sort_by_windows_puts_the_most_first_and_breaks_ties_by_name() {
+        let mut sessions = list(vec![
+            session("web", 2, 0),
+            session("api", 5, 0),
+            session("db", 2, 0),
+            session("notes", 1, 0),
+            session("build", 5, 0),
+        ]);
+
+        sessions.sort(Sort::Windows);
+
+        assert_eq!(sessions.names(), ["api", "build", "db", "web", "notes"]);
+    }
+
+    fn grepped(pattern: &str) -> Vec<String> {
+        let mut sessions = list(vec![
+            session("api", 1, 0),
+            session("db", 1, 0),
+            session("web", 1, 0),
+            session("web2", 1, 0),
+        ]);
+
+        sessions.grep(&Regex::new(pattern).unwrap());
+        sessions.names().into_iter().map(String::from).collect()
+    }
+
+    #[test]
+    fn grep_matches_anywhere_in_the_name() {
+        assert_eq!(grepped("eb"), ["web", "web2"]);
+    }
+
+    #[test]
+    fn grep_honours_anchors() {
+        assert_eq!(grepped("^a"), ["api"]);
+        assert_eq!(grepped("[0-9]$"), ["web2"]);
+    }
+
+    #[test]
+    fn grep_with_alternation_keeps_the_order() {
+        assert_eq!(grepped("^(web|api)$"), ["api", "web"]);
+    }
+
+    #[test]
+    fn grep_that_matches_nothing_leaves_nothing() {
+        assert!(grepped("^zz").is_empty());
+    }
+
+    #[test]
+    fn listing_of_no_output_is_empty() {
+        assert_eq!(SessionList::parse("").listing(), Listing::Empty);
+    }
+
+    #[test]
+    fn listing_of_only_blank_lines_is_empty() {
+        assert_eq!(SessionList::parse("\n  \n\t\n").listing(), Listing::Empty);
+    }
+
+    #[test]
+    fn listing_of_one_session() {
+        let sessions = SessionList::parse("work\t2\t0\t100\t50\t\n");
+
+        assert_eq!(
+            sessions.listing(),
+            Listing::Sessions(vec![String::from("work")])
+        );
+    }
+
+    #[test]
+    fn listing_of_several_sessions_skips_blank_lines() {
+        let sessions = SessionList::parse("work\t2\t0\t100\t50\t\n\nplay\t1\t1\t200\t60\t\n \n");
+
+        assert_eq!(
+            sessions.listing(),
+            Listing::Sessions(vec![String::from("work"), String::from("play")])
+        );
+    }
+
+    #[test]
+    fn listing_of_plain_names() {
+        let sessions = SessionList::parse("work\nplay\n");
+
+        assert_eq!(
+            sessions.listing(),
+            Listing::Sessions(vec![String::from("work"), String::from("play")])
+        );
+    }
+}
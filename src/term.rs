@@ -0,0 +1,270 @@
+use std::env;
+use std::io;
+use std::io::IsTerminal;
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+/// How many columns the terminal is wide, `None` if that can't be told
+///
+/// `COLUMNS` wins when it's set, otherwise the terminal is asked directly
+pub fn width() -> Option<usize> {
+    if let Some(columns) = env::var("COLUMNS")
+        .ok()
+        .and_then(|columns| columns.trim().parse().ok())
+    {
+        return Some(columns).filter(|columns| *columns > 0);
+    }
+
+    // `stty` reports on the terminal it's given as stdin, which we share
+    if !io::stdout().is_terminal() || !io::stdin().is_terminal() {
+        return None;
+    }
+
+    match stty(&["size"]) {
+        // it prints "<rows> <columns>"
+        Ok(output) => output
+            .split_whitespace()
+            .nth(1)
+            .and_then(|columns| columns.parse().ok())
+            .filter(|columns| *columns > 0),
+        Err(_error) => None,
+    }
+}
+
+/// Run `stty` against our terminal and return what it printed
+pub fn stty(args: &[&str]) -> io::Result<String> {
+    // stdin has to be passed on since that's the terminal stty works on
+    let output = Command::new("stty")
+        .args(args)
+        .stdin(Stdio::inherit())
+        .output()?;
+
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    } else {
+        Err(io::Error::other(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ))
+    }
+}
+
+/// Cut `line` down to `width` visible columns, ending it with `…` if anything was cut
+///
+/// ANSI escape sequences (colors and the like) take up no columns so they
+/// are kept but not counted, and a reset is added after a cut so a color
+/// doesn't bleed past the end of the line.
+pub fn truncate_visible(line: &str, width: usize) -> String {
+    if visible_width(line) <= width {
+        return line.to_string();
+    }
+
+    let mut truncated = String::new();
+    let mut visible = 0;
+    let mut styled = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            styled = true;
+            truncated.push(c);
+
+            // CSI sequences run from `ESC [` up to a final byte in `@`..=`~`
+            if chars.peek() == Some(&'[') {
+                for c in chars.by_ref() {
+                    truncated.push(c);
+
+                    if c != '[' && ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            continue;
+        }
+
+        // leave room for the ellipsis
+        if visible + 1 >= width {
+            break;
+        }
+
+        truncated.push(c);
+        visible += 1;
+    }
+
+    if width > 0 {
+        truncated.push('…');
+    }
+
+    if styled {
+        truncated.push_str("\x1b[0m");
+    }
+
+    truncated
+}
+
+/// How many columns `line` takes up once ANSI escape sequences are left out
+pub fn visible_width(line: &str) -> usize {
+    let mut visible = 0;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            if chars.peek() == Some(&'[') {
+                for c in chars.by_ref() {
+                    if c != '[' && ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            continue;
+        }
+
+        visible += 1;
+    }
+
+    visible
+}
+
+/// The colors `paint` knows
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Color {
+    Red,
+    Green,
+    Yellow,
+    /// faint text for things that matter less
+    Dim,
+}
+
+// set by `--color` or `--no-color`
+static COLOR_WHEN: OnceLock<ColorWhen> = OnceLock::new();
+
+/// When to color output, as asked for with `--color`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColorWhen {
+    /// even when the output isn't a terminal, e.g. for `less -R`
+    Always,
+    /// when writing to a terminal and `NO_COLOR` isn't set
+    Auto,
+    Never,
+}
+
+impl FromStr for ColorWhen {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "always" => Ok(ColorWhen::Always),
+            "auto" => Ok(ColorWhen::Auto),
+            "never" => Ok(ColorWhen::Never),
+            _ => Err(format!("unknown color setting '{}'", value)),
+        }
+    }
+}
+
+/// Color output `when` from here on instead of working it out
+pub fn use_color(when: ColorWhen) {
+    let _ = COLOR_WHEN.set(when);
+}
+
+/// Whether output should be colored
+///
+/// Only when writing to a terminal and `NO_COLOR` (https://no-color.org) isn't
+/// set, unless `--color` says otherwise
+pub fn colors_enabled() -> bool {
+    let no_color = env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+
+    should_color(
+        COLOR_WHEN.get().copied().unwrap_or(ColorWhen::Auto),
+        no_color,
+        io::stdout().is_terminal(),
+    )
+}
+
+/// Whether to color given `--color`, whether `NO_COLOR` is set and whether
+/// stdout is a terminal
+///
+/// `always` and `never` win over everything, `NO_COLOR` only turns `auto` off
+fn should_color(when: ColorWhen, no_color: bool, terminal: bool) -> bool {
+    match when {
+        ColorWhen::Always => true,
+        ColorWhen::Never => false,
+        ColorWhen::Auto => terminal && !no_color,
+    }
+}
+
+/// Wrap `text` in the escape codes for `color`, or leave it be when colors are off
+pub fn paint(text: &str, color: Color) -> String {
+    if !colors_enabled() {
+        return text.to_string();
+    }
+
+    let code = match color {
+        Color::Red => "31",
+        Color::Green => "32",
+        Color::Yellow => "33",
+        Color::Dim => "2",
+    };
+
+    format!("\x1b[{}m{}\x1b[0m", code, text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn truncate_visible_leaves_what_fits() {
+        assert_eq!(truncate_visible("hello", 5), "hello");
+        assert_eq!(
+            truncate_visible("\x1b[32mhello\x1b[0m", 5),
+            "\x1b[32mhello\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn truncate_visible_cuts_plain_text_with_an_ellipsis() {
+        assert_eq!(truncate_visible("hello world", 5), "hell…");
+        assert_eq!(visible_width(&truncate_visible("hello world", 5)), 5);
+    }
+
+    #[test]
+    fn truncate_visible_doesnt_count_escapes_and_resets_after_a_cut() {
+        assert_eq!(
+            truncate_visible("\x1b[32mhello\x1b[0m world", 5),
+            "\x1b[32mhell…\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn truncate_visible_copes_with_no_room() {
+        assert_eq!(truncate_visible("hello", 1), "…");
+        assert_eq!(truncate_visible("hello", 0), "");
+        assert_eq!(truncate_visible("\x1b[2mhello", 0), "\x1b[2m\x1b[0m");
+    }
+
+    #[test]
+    fn should_color_follows_always_and_never_whatever_else() {
+        for no_color in [false, true] {
+            for terminal in [false, true] {
+                assert!(should_color(ColorWhen::Always, no_color, terminal));
+                assert!(!should_color(ColorWhen::Never, no_color, terminal));
+            }
+        }
+    }
+
+    #[test]
+    fn should_color_auto_needs_a_terminal_and_no_no_color() {
+        assert!(should_color(ColorWhen::Auto, false, true));
+        assert!(!should_color(ColorWhen::Auto, true, true));
+        assert!(!should_color(ColorWhen::Auto, false, false));
+        assert!(!should_color(ColorWhen::Auto, true, false));
+    }
+
+    #[test]
+    fn color_when_parses_the_flag_values() {
+        assert_eq!("always".parse(), Ok(ColorWhen::Always));
+        assert_eq!("auto".parse(), Ok(ColorWhen::Auto));
+        assert_eq!("never".parse(), Ok(ColorWhen::Never));
+        assert!("sometimes".parse::<ColorWhen>().is_err());
+    }
+}
@@ -0,0 +1,1020 @@
+use std::env;
+use std::fs::{self, File};
+use std::io;
+use std::os::unix::fs::FileTypeExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::str::FromStr;
+use std::sync::OnceLock;
+use std::thread;
+use std::time::Duration;
+
+use crate::config;
+use crate::fs_utils;
+use crate::last_error;
+use crate::paths;
+use crate::prompt;
+use crate::report;
+use crate::sessions::{self, Client, Pane, SessionList, Window};
+
+// the server to talk to instead of the default one, set by --all-sockets once a session is picked
+static SOCKET: OnceLock<PathBuf> = OnceLock::new();
+
+/// Send every tmux command from now on to the server listening on `socket`
+pub fn use_socket(socket: &Path) {
+    let _ = SOCKET.set(socket.to_path_buf());
+}
+
+/// Build a tmux invocation using the configured binary
+pub fn app(args: &[&str]) -> scuttle::App {
+    let socket = SOCKET
+        .get()
+        .map(|socket| vec![String::from("-S"), socket.to_string_lossy().into_owned()])
+        .unwrap_or_default();
+
+    scuttle::App {
+        command: config::get().tmux_bin.clone(),
+        args: socket
+            .into_iter()
+            .chain(args.iter().map(|arg| arg.to_string()))
+            .collect(),
+    }
+}
+
+/// The sockets of every tmux server this user might be running, sorted
+///
+/// tmux keeps them in a `tmux-<uid>` directory under `paths::tmux_socket_dir`
+/// that only its owner can open, so other users' servers are left out by
+/// not being readable
+pub fn sockets() -> Vec<PathBuf> {
+    let entries = match fs::read_dir(paths::tmux_socket_dir()) {
+        Ok(entries) => entries,
+        Err(_error) => return Vec::new(),
+    };
+
+    let mut sockets: Vec<PathBuf> = entries
+        .flatten()
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with("tmux-"))
+        .filter_map(|entry| fs::read_dir(entry.path()).ok())
+        .flat_map(|directory| directory.flatten())
+        .filter(|entry| {
+            entry
+                .file_type()
+                .is_ok_and(|file_type| file_type.is_socket())
+        })
+        .map(|entry| entry.path())
+        .collect();
+
+    sockets.sort();
+    sockets
+}
+
+/// The sessions on the server listening on `socket`
+///
+/// `None` when nothing answers there, e.g. a socket left behind by a server
+/// that has exited
+pub fn list_sessions_on(socket: &Path) -> Option<SessionList> {
+    let socket = socket.to_string_lossy();
+    let format = list_format();
+    let output = scuttle::run_output(&app(&["-S", &socket, "ls", "-F", &format])).ok()?;
+
+    if output.status.success() {
+        Some(SessionList::parse(&String::from_utf8_lossy(&output.stdout)))
+    } else {
+        None
+    }
+}
+
+/// Stop because tmux couldn't be run at all, `error` being why
+///
+/// Not having tmux installed is common enough on a new machine to get its own
+/// message, and the 127 a shell exits with for a command it can't find
+pub fn fail_to_run(error: io::Error) -> ! {
+    let tmux_bin = &config::get().tmux_bin;
+
+    if error.kind() == io::ErrorKind::NotFound {
+        report::fail(
+            format!(
+                "{} not found; please install tmux and ensure it is on your PATH",
+                tmux_bin
+            ),
+            127,
+        );
+    }
+
+    report::fail(format!("could not run {}: {}", tmux_bin, error), 1);
+}
+
+/// Run tmux and return what it printed
+///
+/// A non-zero exit becomes an error carrying tmux's own message
+pub fn output(args: &[&str]) -> io::Result<String> {
+    let tmux = app(args);
+    let output = scuttle::run_output(&tmux)?;
+
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    } else {
+        last_error::record(&tmux, &output);
+
+        Err(io::Error::other(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ))
+    }
+}
+
+/// How long to wait before each retry when the server can't be reached yet
+const BACKOFF_MS: [u64; 3] = [50, 100, 200];
+
+/// Whether tmux's complaint means there's no server at all, and so no sessions
+///
+/// Older tmux says "no server running on <socket>", 3.x says "error connecting
+/// to <socket> (No such file or directory)" when the socket isn't there
+pub fn is_no_server(stderr: &str) -> bool {
+    stderr.contains("no server running")
+        || (stderr.contains("error connecting to") && stderr.contains("No such file or directory"))
+}
+
+/// Whether tmux's complaint means there is a server that just isn't answering yet
+///
+/// That happens for a moment while a server starts or after `kill-server`,
+/// when the socket is there but nothing accepts on it. No server at all isn't
+/// one of these, see `is_no_server`.
+fn is_transient(stderr: &str) -> bool {
+    if is_no_server(stderr) {
+        return false;
+    }
+
+    // tmux says "error connecting to <socket> (Connection refused)" or
+    // "failed to connect to server" depending on the version
+    stderr.contains("Connection refused") || stderr.contains("failed to connect to server")
+}
+
+/// Run tmux like `scuttle::run_output`, retrying with backoff while the server is unreachable
+pub fn run_retrying(args: &[&str]) -> io::Result<Output> {
+    let tmux = app(args);
+    let mut output = scuttle::run_output(&tmux)?;
+
+    for delay in BACKOFF_MS {
+        if output.status.success() || !is_transient(&String::from_utf8_lossy(&output.stderr)) {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(delay));
+        output = scuttle::run_output(&tmux)?;
+    }
+
+    Ok(output)
+}
+
+// `tmux -V` only needs running once however many places ask
+static VERSION: OnceLock<TmuxVersion> = OnceLock::new();
+
+/// A tmux release, compared by major then minor number
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TmuxVersion {
+    pub major: u32,
+    pub minor: u32,
+}
+
+impl TmuxVersion {
+    /// What a version that couldn't be worked out is taken to be, so nothing gets turned off
+    pub const NEWEST: TmuxVersion = TmuxVersion {
+        major: u32::MAX,
+        minor: u32::MAX,
+    };
+
+    pub fn at_least(&self, major: u32, minor: u32) -> bool {
+        *self >= TmuxVersion { major, minor }
+    }
+}
+
+/// Parse `tmux -V` output like `tmux 3.3a` or `tmux next-3.4`
+///
+/// Builds from git say `tmux master` and count as `NEWEST`
+fn parse_version(output: &str) -> TmuxVersion {
+    let number = output
+        .split_whitespace()
+        .nth(1)
+        .unwrap_or_default()
+        .trim_start_matches(|c: char| !c.is_ascii_digit());
+    let mut parts = number.split('.').map(|part| {
+        // drop the letter of patch releases like `3.3a`
+        let digits: String = part.chars().take_while(|c| c.is_ascii_digit()).collect();
+
+        digits.parse::<u32>().ok()
+    });
+
+    match (parts.next().flatten(), parts.next().flatten()) {
+        (Some(major), Some(minor)) => TmuxVersion { major, minor },
+        (Some(major), None) => TmuxVersion { major, minor: 0 },
+        _ => TmuxVersion::NEWEST,
+    }
+}
+
+/// The version of the configured tmux, asked for the first time it's needed
+// tmux -V
+pub fn version() -> TmuxVersion {
+    *VERSION.get_or_init(|| match output(&["-V"]) {
+        Ok(result) => {
+            report::verbose(1, format!("running {}", result.trim()));
+            parse_version(&result)
+        }
+        Err(_error) => TmuxVersion::NEWEST,
+    })
+}
+
+/// The `ls -F` format for `SessionList::parse` that suits this tmux
+fn list_format() -> String {
+    list_format_for(version()).0
+}
+
+/// The `ls -F` format that suits `version`, along with which one it is for `-vv`
+///
+/// Fields from a newer tmux are left empty, which parses as them being unset.
+/// Before 1.8 there are no `#{...}` fields at all, only the names can be listed
+fn list_format_for(version: TmuxVersion) -> (String, &'static str) {
+    if version.at_least(2, 1) {
+        (sessions::LIST_FORMAT.to_string(), "the full format")
+    } else if version.at_least(1, 8) {
+        (
+            sessions::LIST_FORMAT.replace("#{session_last_attached}", ""),
+            "the format without session_last_attached, which needs tmux 2.1",
+        )
+    } else {
+        (
+            sessions::NAME_FORMAT.to_string(),
+            "just the names, tmux before 1.8 has no #{} fields",
+        )
+    }
+}
+
+/// `tmux ls` in the richest format this tmux copes with
+///
+/// The format is picked by version first. If tmux still rejects it, or the
+/// names come back empty or as the format itself, the sessions are listed
+/// again with just their names, which `SessionList::parse` also understands.
+// tmux ls -F "<name> <windows> ..."
+pub fn list_sessions_output() -> io::Result<Output> {
+    let (format, level) = list_format_for(version());
+
+    report::verbose(2, format!("listing sessions with {}", level));
+
+    let output = run_retrying(&["ls", "-F", &format])?;
+
+    if format != sessions::NAME_FORMAT && format_unsupported(&output) {
+        report::verbose(
+            2,
+            "tmux didn't understand that format, listing just the names",
+        );
+
+        // tmux ls -F "#S"
+        return run_retrying(&["ls", "-F", sessions::NAME_FORMAT]);
+    }
+
+    Ok(output)
+}
+
+/// Whether `ls -F` output shows tmux didn't understand the format
+fn format_unsupported(output: &Output) -> bool {
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr).to_lowercase();
+
+        return stderr.contains("unknown") || stderr.contains("format");
+    }
+
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|line| !line.is_empty())
+        .any(|line| {
+            let name = line.split('\t').next().unwrap_or_default();
+
+            // tmux never lists a session without a name, so it didn't fill the field in
+            name.is_empty() || name.starts_with("#{")
+        })
+}
+
+/// The directory a session was started in
+///
+/// Tidied up into an absolute path without `..` or symlinks when it exists,
+/// a session started with a relative `-c` can otherwise report something odd.
+/// A directory that's gone is given as tmux has it.
+// tmux display-message -p -t <session> "#{session_path}"
+pub fn session_path(session: &str) -> io::Result<String> {
+    output(&["display-message", "-p", "-t", session, "#{session_path}"])
+        .map(|path| fs_utils::canonical_or_raw(path.trim_end()))
+}
+
+// set by `--size`, every window of a session is resized to this just before attaching to it
+static ATTACH_SIZE: OnceLock<WindowSize> = OnceLock::new();
+
+/// A window size in cells, written `WxH`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WindowSize {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl FromStr for WindowSize {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("'{}' isn't a size like 200x50", value);
+        let (width, height) = value.split_once(['x', 'X']).ok_or_else(invalid)?;
+        let width: u32 = width.trim().parse().map_err(|_error| invalid())?;
+        let height: u32 = height.trim().parse().map_err(|_error| invalid())?;
+
+        if width == 0 || height == 0 {
+            return Err(format!("'{}' needs a width and height above 0", value));
+        }
+
+        Ok(WindowSize { width, height })
+    }
+}
+
+/// Resize the windows of every session attached to from here on
+pub fn resize_on_attach(size: WindowSize) {
+    let _ = ATTACH_SIZE.set(size);
+}
+
+/// Give every window in `session` the size `--size` asked for
+///
+/// This is best effort, attaching still goes ahead if it doesn't work
+// tmux resize-window -t <session>:<index> -x <width> -y <height>
+fn apply_attach_size(session: &str) {
+    let size = match ATTACH_SIZE.get() {
+        Some(size) => size,
+        None => return,
+    };
+
+    if !version().at_least(2, 9) {
+        report::error("this tmux is older than 2.9 and can't resize windows, ignoring --size");
+        return;
+    }
+
+    let windows = match list_windows(Some(session)) {
+        Ok(windows) => windows,
+        Err(error) => {
+            report::error(format!("could not resize {}: {}", session, error));
+            return;
+        }
+    };
+
+    let width = size.width.to_string();
+    let height = size.height.to_string();
+
+    for window in windows {
+        let target = format!("{}:{}", session, window.index);
+
+        if let Err(error) = output(&["resize-window", "-t", &target, "-x", &width, "-y", &height]) {
+            report::error(format!("could not resize {}: {}", target, error));
+        }
+    }
+}
+
+// set by `--attach-window-cmd`, run in a window of its own in every session attached to
+static ATTACH_WINDOW_COMMAND: OnceLock<String> = OnceLock::new();
+
+/// Open a window running `command` in every session attached to from here on
+pub fn run_on_attach(command: &str) {
+    let _ = ATTACH_WINDOW_COMMAND.set(command.to_string());
+}
+
+/// Open the window `--attach-window-cmd` asked for in `session`
+///
+/// tmux makes a new window the current one, so it's what the attach shows.
+/// `false` if it couldn't be opened, which has been reported.
+// tmux new-window -t <session>: <command>
+fn open_attach_window(session: &str) -> bool {
+    let command = match ATTACH_WINDOW_COMMAND.get() {
+        Some(command) => command,
+        None => return true,
+    };
+
+    let target = format!("{}:", exact(session));
+
+    match output(&["new-window", "-t", &target, command]) {
+        Ok(_output) => true,
+        Err(_error) if !session_exists(session) => {
+            report::error(format!("session '{}' no longer exists", session));
+            false
+        }
+        Err(error) => {
+            report::error(format!(
+                "could not open a window for {} in {}: {}",
+                command, session, error
+            ));
+            false
+        }
+    }
+}
+
+/// Whether attaching to `session` should detach the clients already on it
+///
+/// Only with `grab` configured, and only once the user has seen who is
+/// attached and agreed. With nobody else attached there's nothing to detach.
+pub fn grab_others(session: &str) -> bool {
+    if !config::get().grab {
+        return false;
+    }
+
+    match list_clients(session) {
+        Ok(clients) if clients.is_empty() => false,
+        Ok(clients) => confirm_detach(session, &clients),
+        Err(error) => {
+            report::error(format!(
+                "could not list the clients of {}, not detaching them: {}",
+                session, error
+            ));
+            false
+        }
+    }
+}
+
+/// Show the `clients` attached to `session` and ask before detaching them
+pub fn confirm_detach(session: &str, clients: &[Client]) -> bool {
+    prompt::say(&format!("Attached to {}:", session));
+
+    for client in clients {
+        prompt::say(&format!("  {} ({})", client.name, client.terminal));
+    }
+
+    let question = if clients.len() == 1 {
+        String::from("Detach this client?")
+    } else {
+        format!("Detach these {} clients?", clients.len())
+    };
+
+    prompt::confirm_destructive(&question)
+}
+
+/// The tmux invocation that attaches to a session
+///
+/// With `detach_others` every other client is detached first
+pub fn attach_app(session: &str, detach_others: bool) -> scuttle::App {
+    let mut args = attach_command(false, &exact(session));
+
+    // `-d` detaches the others as part of attaching, so it can't catch the client being created
+    // tmux attach [-d] -t <session>
+    if detach_others {
+        args.insert(1, String::from("-d"));
+    }
+
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    app(&args)
+}
+
+/// The tmux arguments that bring `name` up, switching the current client to it
+/// `in_tmux` and attaching otherwise
+///
+/// `name` is used as the target as it is, `exact` makes it match only that session
+pub fn attach_command(in_tmux: bool, name: &str) -> Vec<String> {
+    let command = if in_tmux { "switch-client" } else { "attach" };
+
+    vec![command.to_string(), String::from("-t"), name.to_string()]
+}
+
+/// A target that only matches the session called exactly `session`
+///
+/// A plain name also matches sessions it's the start of when there isn't one
+/// by that name (yet), `=` turns that off on tmux 2.1 and later
+pub fn exact(session: &str) -> String {
+    if version().at_least(2, 1) {
+        format!("={}", session)
+    } else {
+        session.to_string()
+    }
+}
+
+/// Wait for a session that was just created to be listed, `false` if it never is
+///
+/// The `new-session` that starts the server can return before that server
+/// answers for its sessions, so this asks a few times before giving up
+pub fn wait_for_session(session: &str) -> bool {
+    for delay in BACKOFF_MS {
+        if session_exists(session) {
+            return true;
+        }
+
+        thread::sleep(Duration::from_millis(delay));
+    }
+
+    session_exists(session)
+}
+
+/// Whether `tm` is running in a tmux pane, tmux sets `$TMUX` in every one it starts
+pub fn inside_tmux() -> bool {
+    env::var_os("TMUX").is_some_and(|tmux| !tmux.is_empty())
+}
+
+/// Move the client `tm` is running in over to `session`
+///
+/// Attaching from inside tmux would start a tmux within tmux, so this is what
+/// `attach` does there instead. It returns straight away.
+// tmux switch-client -t <session>
+fn switch_client(session: &str) -> bool {
+    let args = attach_command(true, &exact(session));
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    match output(&args) {
+        Ok(_output) => true,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => fail_to_run(error),
+        Err(error) => {
+            report::error(format!("could not switch to {}: {}", session, error));
+            false
+        }
+    }
+}
+
+/// Attach to a session, blocking until the user detaches
+///
+/// Inside tmux the current client switches to it instead. Returns `false` if
+/// tmux couldn't attach, after saying why: either the session has gone or
+/// what tmux itself said
+pub fn attach(session: &str) -> bool {
+    if !open_attach_window(session) {
+        return false;
+    }
+
+    apply_attach_size(session);
+
+    if inside_tmux() {
+        return switch_client(session);
+    }
+
+    let tmux_attach = attach_app(session, grab_others(session));
+    let mut command = Command::new(&tmux_attach.command);
+
+    // tmux only needs the terminal on stdin and stdout, its complaints are kept to explain a failure
+    command.args(&tmux_attach.args).stderr(Stdio::piped());
+
+    // when the choice was piped in tmux still needs the terminal to attach to
+    if !prompt::is_interactive() {
+        match File::open(attach_tty()) {
+            Ok(tty) => {
+                command.stdin(tty);
+            }
+            Err(error) => {
+                report::error(format!(
+                    "could not open the terminal to attach with: {}",
+                    error
+                ));
+                return false;
+            }
+        }
+    }
+
+    let output = match command.spawn().and_then(|child| child.wait_with_output()) {
+        Ok(output) => output,
+        Err(error) => fail_to_run(error),
+    };
+
+    if output.status.success() {
+        return true;
+    }
+
+    last_error::record(&tmux_attach, &output);
+
+    // most likely it was killed since the list was shown
+    if !session_exists(session) {
+        report::error(format!("session '{}' no longer exists", session));
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+
+        report::error(format!(
+            "could not attach to {}: {}",
+            session,
+            stderr.trim()
+        ));
+    }
+
+    false
+}
+
+/// The terminal tmux attaches with when stdin isn't one, `/dev/tty` unless `TM_TTY` says otherwise
+///
+/// Without a controlling terminal, as under a test harness, `TM_TTY=/dev/null`
+/// lets the attach go ahead for a tmux that doesn't need a real one.
+fn attach_tty() -> PathBuf {
+    env::var_os("TM_TTY")
+        .filter(|tty| !tty.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("/dev/tty"))
+}
+
+/// Whether a session with exactly this name is running
+pub fn session_exists(session: &str) -> bool {
+    // with no server running nothing exists
+    running_sessions().names().contains(&session)
+}
+
+/// Every session tmux is running
+pub fn list_sessions() -> io::Result<SessionList> {
+    let output = list_sessions_output()?;
+
+    if output.status.success() {
+        Ok(SessionList::parse(&String::from_utf8_lossy(&output.stdout)))
+    } else {
+        Err(io::Error::other(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ))
+    }
+}
+
+/// Every session tmux is running, none when there's no server to ask
+///
+/// Stops like `fail_to_run` when tmux itself can't be run, so a missing tmux
+/// doesn't look like one without sessions
+pub fn running_sessions() -> SessionList {
+    match list_sessions() {
+        Ok(sessions) => sessions,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => fail_to_run(error),
+        Err(_error) => SessionList::default(),
+    }
+}
+
+/// The windows of `session`, or of every session when `None`
+// tmux list-windows [-t <session> | -a] -F "<session> <index> ..."
+pub fn list_windows(session: Option<&str>) -> io::Result<Vec<Window>> {
+    let result = match session {
+        Some(session) => output(&["list-windows", "-t", session, "-F", sessions::WINDOW_FORMAT])?,
+        None => output(&["list-windows", "-a", "-F", sessions::WINDOW_FORMAT])?,
+    };
+
+    Ok(sessions::parse_windows(&result))
+}
+
+/// Every pane of every session
+// tmux list-panes -a -F "<session> <window> <index> <command>"
+pub fn list_panes() -> io::Result<Vec<Pane>> {
+    output(&["list-panes", "-a", "-F", sessions::PANE_FORMAT])
+        .map(|result| sessions::parse_panes(&result))
+}
+
+/// The text in the active pane of a session, with its whole history when `scrollback`
+// tmux capture-pane -p [-S -] -t <session>
+pub fn capture_pane(session: &str, scrollback: bool) -> io::Result<String> {
+    if scrollback {
+        output(&["capture-pane", "-p", "-S", "-", "-t", session])
+    } else {
+        output(&["capture-pane", "-p", "-t", session])
+    }
+}
+
+/// The session of the tmux client `tm` is running in, `None` outside tmux
+// tmux display-message -p "#{session_name}"
+pub fn current_session() -> Option<String> {
+    if !inside_tmux() {
+        return None;
+    }
+
+    output(&["display-message", "-p", "#{session_name}"])
+        .ok()
+        .map(|name| name.trim_end().to_string())
+        .filter(|name| !name.is_empty())
+}
+
+/// Add a window to the end of `session`, started in `path`
+// tmux new-window -t <session>: -c <path> [-n <name>]
+pub fn new_window(session: &str, path: &Path, name: Option<&str>) -> io::Result<()> {
+    let target = format!("{}:", session);
+    let path = path.to_string_lossy();
+    let mut args = vec!["new-window", "-t", &target, "-c", &path];
+
+    if let Some(name) = name {
+        args.extend(["-n", name]);
+    }
+
+    output(&args).map(|_output| ())
+}
+
+/// Make a window the current one in its session
+// tmux select-window -t <session>:<index>
+pub fn select_window(target: &str) -> io::Result<()> {
+    output(&["select-window", "-t", target]).map(|_output| ())
+}
+
+/// The clients attached to a session
+// tmux list-clients -t <session> -F CLIENT_FORMAT
+pub fn list_clients(session: &str) -> io::Result<Vec<Client>> {
+    let target = exact(session);
+
+    output(&["list-clients", "-t", &target, "-F", sessions::CLIENT_FORMAT])
+        .map(|clients| sessions::parse_clients(&clients))
+}
+
+/// Detach every client attached to a session
+// tmux detach-client -s <session>
+pub fn detach_clients(session: &str) -> io::Result<()> {
+    output(&["detach-client", "-s", session]).map(|_output| ())
+}
+
+/// Why a window couldn't be linked
+pub enum LinkWindowError {
+    /// the destination already has a window at that index
+    IndexInUse,
+    /// tmux refused, with its message
+    Failed(String),
+    /// tmux couldn't be run at all
+    Io(io::Error),
+}
+
+/// Link the window `source` (`session:index`) into `target` (`session:[index]`)
+///
+/// With `kill` a window already at the target index is replaced
+// tmux link-window [-k] -s <source> -t <target>
+pub fn link_window(source: &str, target: &str, kill: bool) -> Result<(), LinkWindowError> {
+    let mut args = vec!["link-window"];
+
+    if kill {
+        args.push("-k");
+    }
+
+    args.extend(["-s", source, "-t", target]);
+
+    let output = match scuttle::run_output(&app(&args)) {
+        Ok(output) => output,
+        Err(error) => return Err(LinkWindowError::Io(error)),
+    };
+
+    if output.status.success() {
+        return Ok(());
+    }
+
+    let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+
+    // tmux says "index in use: <index>"
+    if stderr.contains("index in use") {
+        Err(LinkWindowError::IndexInUse)
+    } else {
+        Err(LinkWindowError::Failed(stderr))
+    }
+}
+
+/// Move the window `source` (`session:index`) to `target` (`session:[index]`)
+///
+/// A target without an index puts the window at the next free one. Moving
+/// a session's last window away ends that session.
+// tmux move-window -s <source> -t <target>
+pub fn move_window(source: &str, target: &str) -> io::Result<()> {
+    output(&["move-window", "-s", source, "-t", target]).map(|_output| ())
+}
+
+/// Send keys to the active pane of a session as if they were typed there
+///
+/// tmux presses anything that's a key name (`Enter`, `C-c`, ...) and types the rest
+// tmux send-keys -t <session> <keys>...
+pub fn send_keys(session: &str, keys: &[&str]) -> io::Result<()> {
+    let mut args = vec!["send-keys", "-t", session];
+
+    args.extend(keys);
+
+    output(&args).map(|_output| ())
+}
+
+/// Set a tmux option for one session, leaving every other session as it was
+// tmux set-option -t <session> <option> <value>
+pub fn set_option(session: &str, option: &str, value: &str) -> io::Result<()> {
+    output(&["set-option", "-t", &exact(session), option, value]).map(|_output| ())
+}
+
+/// Give a session a new name
+// tmux rename-session -t <session> <name>
+pub fn rename_session(session: &str, name: &str) -> io::Result<()> {
+    output(&["rename-session", "-t", &exact(session), name]).map(|_output| ())
+}
+
+/// Set the note shown next to a session, or clear it with `None`
+// tmux set-option -t <session> @note <text>
+// tmux set-option -t <session> -u @note
+pub fn set_note(session: &str, note: Option<&str>) -> io::Result<()> {
+    match note {
+        // a tab would be taken for the end of the field when the list is parsed
+        Some(note) => output(&[
+            "set-option",
+            "-t",
+            session,
+            "@note",
+            &note.replace('\t', " "),
+        ]),
+        None => output(&["set-option", "-t", session, "-u", "@note"]),
+    }
+    .map(|_output| ())
+}
+
+/// Kill a session and every window in it
+// tmux kill-session -t <session>
+pub fn kill_session(session: &str) -> io::Result<()> {
+    output(&["kill-session", "-t", session]).map(|_output| ())
+}
+
+/// Stop the tmux server, which kills every session it has
+// tmux kill-server
+pub fn kill_server() -> io::Result<()> {
+    output(&["kill-server"]).map(|_output| ())
+}
+
+/// Why a session couldn't be created
+pub enum NewSessionError {
+    /// a session with that name already exists
+    Duplicate,
+    /// tmux refused to create it, with tmux's message
+    Failed(String),
+    /// tmux couldn't be run at all
+    Io(io::Error),
+}
+
+/// The tmux invocation that starts a detached session called `name` in `path`
+///
+/// The first window runs the configured `shell` when there is one
+// tmux new-session -d -s <name> -c <path> [<shell>]
+pub fn new_session_app(name: &str, path: &Path) -> scuttle::App {
+    let path = path.to_string_lossy();
+    let mut args = vec!["new-session", "-d", "-s", name, "-c", &path];
+
+    // so the first window has them too, `new_session` sets them afterwards on older tmux
+    let copied: Vec<String> = if version().at_least(3, 2) {
+        copied_env()
+            .iter()
+            .map(|(var, value)| format!("{}={}", var, value))
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    copied.iter().for_each(|pair| args.extend(["-e", pair]));
+
+    // tmux hands a lone command to `sh -c` so quote it in case the path has spaces
+    let shell = &config::get().shell;
+    let quoted = quote(shell);
+
+    if !shell.is_empty() {
+        args.push(&quoted);
+    }
+
+    app(&args)
+}
+
+/// The `copy_env_vars` that are set here, when `copy_env` is on
+fn copied_env() -> Vec<(String, String)> {
+    let config = config::get();
+
+    if !config.copy_env {
+        return Vec::new();
+    }
+
+    config
+        .copy_env_vars
+        .iter()
+        .filter_map(|var| env::var(var).ok().map(|value| (var.clone(), value)))
+        .collect()
+}
+
+/// `app` written out the way it could be typed into a shell
+pub fn command_line(app: &scuttle::App) -> String {
+    let mut words = vec![quote_if_needed(&app.command)];
+
+    words.extend(app.args.iter().map(|arg| quote_if_needed(arg)));
+    words.join(" ")
+}
+
+/// `word` in single quotes, safe to hand to `sh`
+fn quote(word: &str) -> String {
+    format!("'{}'", word.replace('\'', "'\\''"))
+}
+
+/// `word` as is when `sh` would read it back the same, otherwise quoted
+fn quote_if_needed(word: &str) -> String {
+    let plain = !word.is_empty()
+        && word
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./:=@%+,#{}".contains(c));
+
+    if plain {
+        word.to_string()
+    } else {
+        quote(word)
+    }
+}
+
+/// Start a detached session called `name` in `path`
+pub fn new_session(name: &str, path: &Path) -> Result<(), NewSessionError> {
+    let tmux = new_session_app(name, path);
+    let created = match scuttle::run_output(&tmux) {
+        Ok(output) => output,
+        Err(error) => return Err(NewSessionError::Io(error)),
+    };
+
+    if created.status.success() {
+        // tmux before 3.2 can't be given them up front, so only windows opened later see these
+        // tmux set-environment -t <name> <var> <value>
+        if !version().at_least(3, 2) {
+            for (var, value) in copied_env() {
+                if let Err(error) = output(&["set-environment", "-t", name, &var, &value]) {
+                    report::error(format!("could not copy {} into {}: {}", var, name, error));
+                }
+            }
+        }
+
+        return Ok(());
+    }
+
+    Err(new_session_error(&tmux, &created))
+}
+
+/// Start a session `name` in the same group as the session `group`, detached
+///
+/// Grouped sessions share their windows, so a window opened or closed in one
+/// is opened or closed in the other, but each shows its own current window
+// tmux new-session -d -s <name> -t <group>
+pub fn new_grouped_session(name: &str, group: &str) -> Result<(), NewSessionError> {
+    let target = exact(group);
+    let tmux = app(&["new-session", "-d", "-s", name, "-t", &target]);
+
+    match scuttle::run_output(&tmux) {
+        Ok(created) if created.status.success() => Ok(()),
+        Ok(created) => Err(new_session_error(&tmux, &created)),
+        Err(error) => Err(NewSessionError::Io(error)),
+    }
+}
+
+/// Why tmux refused to create a session, going by what it said
+fn new_session_error(tmux: &scuttle::App, created: &Output) -> NewSessionError {
+    last_error::record(tmux, created);
+
+    let stderr = String::from_utf8_lossy(&created.stderr).trim().to_string();
+
+    // tmux says "duplicate session: <name>"
+    if stderr.starts_with("duplicate session") {
+        NewSessionError::Duplicate
+    } else {
+        NewSessionError::Failed(stderr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn list_format_for_leaves_out_what_older_tmux_lacks() {
+        let (format, _level) = list_format_for(TmuxVersion { major: 3, minor: 3 });
+        assert_eq!(format, sessions::LIST_FORMAT);
+
+        let (format, level) = list_format_for(TmuxVersion { major: 1, minor: 9 });
+        assert!(!format.contains("#{session_last_attached}"));
+        assert!(level.contains("without session_last_attached"), "{}", level);
+
+        let (format, level) = list_format_for(TmuxVersion { major: 1, minor: 6 });
+        assert_eq!(format, sessions::NAME_FORMAT);
+        assert!(level.starts_with("just the names"), "{}", level);
+    }
+
+    #[test]
+    fn is_transient_isnt_fooled_by_no_server() {
+        assert!(!is_transient("no server running on /tmp/tmux-1000/default"));
+        assert!(!is_transient(
+            "error connecting to /tmp/tmux-0/default (No such file or directory)"
+        ));
+    }
+
+    #[test]
+    fn is_no_server_knows_both_wordings() {
+        assert!(is_no_server("no server running on /tmp/tmux-1000/default"));
+        assert!(is_no_server(
+            "error connecting to /tmp/tmux-0/default (No such file or directory)"
+        ));
+        assert!(!is_no_server(
+            "error connecting to /tmp/tmux-0/default (Connection refused)"
+        ));
+    }
+
+    #[test]
+    fn is_transient_retries_a_server_that_isnt_answering() {
+        assert!(is_transient(
+            "error connecting to /tmp/tmux-1000/default (Connection refused)"
+        ));
+        assert!(is_transient("failed to connect to server"));
+    }
+
+    #[test]
+    fn is_transient_gives_up_on_other_errors() {
+        assert!(!is_transient("can't find session: work"));
+        assert!(!is_transient("unknown command: lss"));
+        assert!(!is_transient(""));
+    }
+
+    #[test]
+    fn attach_command_attaches_outside_tmux() {
+        assert_eq!(attach_command(false, "=work"), ["attach", "-t", "=work"]);
+    }
+
+    #[test]
+    fn attach_command_switches_the_client_inside_tmux() {
+        assert_eq!(
+            attach_command(true, "=work"),
+            ["switch-client", "-t", "=work"]
+        );
+    }
+}
@@ -0,0 +1,94 @@
+use std::io;
+
+use crate::sessions::{Pane, Window};
+use crate::term::{self, Color};
+use crate::tmux;
+
+/// A session with everything in it, for `--tree`
+pub struct SessionNode {
+    pub name: String,
+    pub windows: Vec<WindowNode>,
+}
+
+/// A window of a `SessionNode` and its panes
+pub struct WindowNode {
+    pub window: Window,
+    pub panes: Vec<Pane>,
+}
+
+/// Gather every session, window and pane into one tree
+///
+/// Three tmux calls whatever the number of sessions, the windows and panes
+/// are listed for all of them at once and sorted into place here
+pub fn gather() -> io::Result<Vec<SessionNode>> {
+    let sessions = tmux::list_sessions()?;
+    let windows = tmux::list_windows(None)?;
+    let panes = tmux::list_panes()?;
+
+    Ok(build(&sessions.names(), windows, panes))
+}
+
+/// Sort `windows` and `panes` under the `sessions` they belong to, in tmux's order
+fn build(sessions: &[&str], windows: Vec<Window>, panes: Vec<Pane>) -> Vec<SessionNode> {
+    let mut nodes: Vec<SessionNode> = sessions
+        .iter()
+        .map(|name| SessionNode {
+            name: name.to_string(),
+            windows: Vec::new(),
+        })
+        .collect();
+
+    for window in windows {
+        if let Some(node) = nodes.iter_mut().find(|node| node.name == window.session) {
+            node.windows.push(WindowNode {
+                window,
+                panes: Vec::new(),
+            });
+        }
+    }
+
+    for pane in panes {
+        let window = nodes
+            .iter_mut()
+            .filter(|node| node.name == pane.session)
+            .flat_map(|node| node.windows.iter_mut())
+            .find(|node| node.window.index == pane.window);
+
+        if let Some(window) = window {
+            window.panes.push(pane);
+        }
+    }
+
+    nodes
+}
+
+/// The tree indented by level, sessions then windows then panes
+pub fn render(nodes: &[SessionNode]) -> String {
+    let mut tree = String::new();
+
+    for session in nodes {
+        tree.push_str(&format!(
+            "{} ({} windows)\n",
+            term::paint(&session.name, Color::Green),
+            session.windows.len()
+        ));
+
+        for node in &session.windows {
+            let window = format!("{}: {}", node.window.index, node.window.name);
+
+            tree.push_str(&format!(
+                "  {} ({} panes)\n",
+                term::paint(&window, Color::Yellow),
+                node.panes.len()
+            ));
+
+            for pane in &node.panes {
+                let pane = format!("{}: {}", pane.index, pane.command);
+
+                tree.push_str(&format!("    {}\n", term::paint(&pane, Color::Dim)));
+            }
+        }
+    }
+
+    tree
+}
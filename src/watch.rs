@@ -0,0 +1,198 @@
+use std::io;
+use std::io::{IsTerminal, Read, Write};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+use crate::commands;
+use crate::report;
+use crate::sessions::{Session, SessionList};
+use crate::term::{self, Color};
+use crate::time_utils;
+use crate::tmux;
+
+// clear the screen and move the cursor to the top left
+const CLEAR: &str = "\x1b[2J\x1b[H";
+
+/// Redraw the session list every `interval` until `q` is pressed
+///
+/// With `active_only` only the sessions somebody is attached to are shown.
+/// This only looks, nothing can be attached to or changed from here
+pub fn run(interval: Duration, active_only: bool) {
+    if !io::stdin().is_terminal() {
+        report::fail("--watch needs a terminal to read the q key from", 1);
+    }
+
+    // remember how the terminal was set up so it can be put back exactly
+    let saved = match term::stty(&["-g"]) {
+        Ok(saved) => saved,
+        Err(error) => {
+            report::fail(
+                format!("could not read the terminal settings: {}", error),
+                1,
+            );
+        }
+    };
+
+    // hand over keys as they're pressed without echoing them, ctrl-c included so
+    // quitting that way still puts the terminal back
+    if let Err(error) = term::stty(&["-icanon", "-echo", "-isig", "min", "1"]) {
+        report::fail(format!("could not set up the terminal: {}", error), 1);
+    }
+
+    let (sender, receiver) = mpsc::channel();
+
+    thread::spawn(move || {
+        let mut key = [0u8; 1];
+        let mut stdin = io::stdin();
+
+        while let Ok(1) = stdin.read(&mut key) {
+            if sender.send(key[0]).is_err() {
+                break;
+            }
+        }
+    });
+
+    loop {
+        draw(interval, active_only);
+
+        match receiver.recv_timeout(interval) {
+            // 3 is ctrl-c
+            Ok(b'q') | Ok(b'Q') | Ok(3) => break,
+            // any other key just redraws straight away
+            Ok(_key) => (),
+            Err(RecvTimeoutError::Timeout) => (),
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
+
+    if let Err(error) = term::stty(&[&saved]) {
+        report::error(format!(
+            "could not restore the terminal settings: {}",
+            error
+        ));
+    }
+}
+
+/// One refresh of `--watch --json`
+#[derive(Serialize)]
+struct Snapshot {
+    /// unix time the sessions were listed
+    timestamp: u64,
+    sessions: Vec<Session>,
+}
+
+/// Print the sessions as one line of JSON every `interval`, for other programs to read
+///
+/// Runs until whatever is reading stops or `tm` is interrupted
+pub fn run_json(interval: Duration, active_only: bool) {
+    loop {
+        let snapshot = Snapshot {
+            timestamp: now(),
+            sessions: list(active_only).sessions,
+        };
+
+        let line = match serde_json::to_string(&snapshot) {
+            Ok(line) => line,
+            Err(error) => report::fail(format!("could not write JSON: {}", error), 1),
+        };
+
+        let stdout = io::stdout();
+        let mut stdout = stdout.lock();
+
+        // flushed every time so a reader sees each refresh as it happens
+        commands::exit_on_broken_pipe(writeln!(stdout, "{}", line));
+        commands::exit_on_broken_pipe(stdout.flush());
+        drop(stdout);
+
+        thread::sleep(interval);
+    }
+}
+
+/// Seconds since the unix epoch
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|now| now.as_secs())
+        .unwrap_or_default()
+}
+
+/// The sessions to show, only attached ones with `active_only`
+fn list(active_only: bool) -> SessionList {
+    // with no server running there's simply nothing to show
+    let mut sessions = tmux::running_sessions();
+
+    if active_only {
+        sessions.retain_attached();
+    }
+
+    sessions
+}
+
+/// Draw one frame of the dashboard
+fn draw(interval: Duration, active_only: bool) {
+    let now = now();
+    let sessions = list(active_only);
+
+    print!("{}", CLEAR);
+    println!(
+        "{}",
+        term::paint(
+            &format!(
+                "tmux sessions at {}, every {}s, q to quit",
+                time_utils::format_timestamp(now),
+                interval.as_secs()
+            ),
+            Color::Dim
+        )
+    );
+    println!();
+    print!("{}", render(&sessions));
+
+    match io::stdout().flush() {
+        Ok(_result) => (),
+        Err(error) => report::fail(format!("could not write the output: {}", error), 1),
+    };
+}
+
+/// The session table, one line per session
+pub fn render(sessions: &SessionList) -> String {
+    if sessions.sessions.is_empty() {
+        return String::from("No existing tmux sessions found.\n");
+    }
+
+    let width = sessions
+        .sessions
+        .iter()
+        .map(|session| session.name.chars().count())
+        .max()
+        .unwrap_or_default()
+        .max("SESSION".len());
+
+    let mut table = format!(
+        "{:<width$}  {:>7}  ATTACHED\n",
+        "SESSION",
+        "WINDOWS",
+        width = width
+    );
+
+    for session in &sessions.sessions {
+        let attached = match session.attached {
+            0 => String::from("no"),
+            1 => String::from("yes"),
+            clients => format!("yes ({} clients)", clients),
+        };
+
+        table.push_str(&format!(
+            "{:<width$}  {:>7}  {}\n",
+            session.name,
+            session.windows,
+            attached,
+            width = width
+        ));
+    }
+
+    table
+}
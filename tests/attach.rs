@@ -0,0 +1,51 @@
+mod common;
+
+use common::{stderr, Sandbox};
+
+const SESSIONS: [(&str, u32, u32); 2] = [("work", 2, 0), ("play", 1, 0)];
+
+fn attached(sandbox: &Sandbox) -> Vec<String> {
+    sandbox
+        .tmux_calls()
+        .into_iter()
+        .filter(|call| call.starts_with("attach") || call.starts_with("switch-client"))
+        .collect()
+}
+
+#[test]
+fn attach_attaches_to_the_session_named() {
+    let sandbox = Sandbox::new("attach-outside");
+    let mut tm = sandbox.with_stub_tmux(&SESSIONS).tm(&["attach", "work"]);
+
+    // the stub tmux doesn't need the terminal a test may not have
+    let output = tm.env("TM_TTY", "/dev/null").output().unwrap();
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(attached(&sandbox), ["attach -t =work"]);
+}
+
+#[test]
+fn attach_switches_the_client_inside_tmux() {
+    let sandbox = Sandbox::new("attach-inside");
+    let output = sandbox
+        .with_stub_tmux(&SESSIONS)
+        .tm(&["a", "play"])
+        .env("TMUX", "/tmp/tmux-1000/default,1234,0")
+        .output()
+        .unwrap();
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(attached(&sandbox), ["switch-client -t =play"]);
+}
+
+#[test]
+fn attach_refuses_a_session_that_isnt_running() {
+    let sandbox = Sandbox::new("attach-missing");
+    let output = sandbox
+        .with_stub_tmux(&SESSIONS)
+        .run(&["attach", "wrok"], "");
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).contains("no session named wrok"));
+    assert!(attached(&sandbox).is_empty());
+}
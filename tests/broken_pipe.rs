@@ -0,0 +1,37 @@
+mod common;
+
+use std::io::{BufRead, BufReader};
+use std::process::Stdio;
+
+use common::{stderr, Sandbox};
+
+#[test]
+fn list_names_stops_quietly_when_the_reader_goes_away() {
+    // far more than a pipe holds, so tm is still writing when the reader stops
+    let names: Vec<String> = (0..20_000)
+        .map(|index| format!("session-{}", index))
+        .collect();
+    let sessions: Vec<(&str, u32, u32)> = names.iter().map(|name| (name.as_str(), 1, 0)).collect();
+
+    let sandbox = Sandbox::new("broken-pipe");
+    let mut child = sandbox
+        .with_stub_tmux(&sessions)
+        .tm(&["--list-names"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    // like `head -n 1`
+    let mut first = String::new();
+    let mut reader = BufReader::new(child.stdout.take().unwrap());
+
+    reader.read_line(&mut first).unwrap();
+    drop(reader);
+
+    let output = child.wait_with_output().unwrap();
+
+    assert_eq!(first, "session-0\n");
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    assert_eq!(stderr(&output), "");
+}
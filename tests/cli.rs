@@ -0,0 +1,68 @@
+mod common;
+
+use common::{stderr, stdout, Sandbox};
+
+#[test]
+fn help_shows_the_usage_and_options() {
+    let sandbox = Sandbox::new("cli-help");
+    let output = sandbox.run(&["--help"], "");
+
+    assert!(output.status.success(), "{}", stderr(&output));
+
+    let help = stdout(&output);
+
+    for section in ["USAGE:", "OPTIONS:", "--help", "--version", "TOPICS:"] {
+        assert!(help.contains(section), "no {} in:\n{}", section, help);
+    }
+}
+
+#[test]
+fn version_prints_the_crate_version() {
+    let sandbox = Sandbox::new("cli-version");
+    let output = sandbox.run(&["--version"], "");
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        format!("tm {}\n", env!("CARGO_PKG_VERSION"))
+    );
+}
+
+#[test]
+fn an_unknown_flag_is_an_error_naming_it() {
+    let sandbox = Sandbox::new("cli-unknown");
+    let output = sandbox.run(&["--invalid"], "");
+
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(stdout(&output), "");
+    assert!(
+        stderr(&output).contains("Found argument '--invalid'"),
+        "{}",
+        stderr(&output)
+    );
+}
+
+#[test]
+fn verbose_twice_says_which_list_format_is_used() {
+    let sandbox = Sandbox::new("cli-verbose");
+    let output = sandbox
+        .with_stub_tmux(&[("work", 1, 0)])
+        .run(&["-vv", "--list-names"], "");
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "work\n");
+    assert_eq!(
+        stderr(&output),
+        "tm: running tmux 3.3a\ntm: listing sessions with the full format\n"
+    );
+}
+
+#[test]
+fn verbose_once_only_says_which_tmux_was_found() {
+    let sandbox = Sandbox::new("cli-verbose-once");
+    let output = sandbox
+        .with_stub_tmux(&[("work", 1, 0)])
+        .run(&["--verbose", "--list-names"], "");
+
+    assert_eq!(stderr(&output), "tm: running tmux 3.3a\n");
+}
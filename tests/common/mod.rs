@@ -0,0 +1,127 @@
+#![allow(dead_code)]
+
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+/// A directory of its own for one test, standing in for `$HOME` and removed again afterwards
+pub struct Sandbox {
+    pub root: PathBuf,
+}
+
+impl Sandbox {
+    pub fn new(name: &str) -> Sandbox {
+        let root = std::env::temp_dir().join(format!("tm-test-{}-{}", std::process::id(), name));
+
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("bin")).unwrap();
+
+        Sandbox { root }
+    }
+
+    /// `tm` with only this sandbox to go on, no config, no tmux but a stub one when
+    /// `with_stub_tmux` has been called and nothing on stdin
+    pub fn tm(&self, args: &[&str]) -> Command {
+        let mut tm = Command::new(env!("CARGO_BIN_EXE_tm"));
+
+        tm.args(args)
+            .env_clear()
+            .env("PATH", self.root.join("bin"))
+            .env("HOME", &self.root)
+            .env("XDG_CONFIG_HOME", self.root.join("config"))
+            .env("XDG_STATE_HOME", self.root.join("state"))
+            .env("TMUX_TMPDIR", &self.root)
+            .stdin(Stdio::null());
+
+        tm
+    }
+
+    /// Run `tm` with `input` on stdin
+    pub fn run(&self, args: &[&str], input: &str) -> Output {
+        feed(self.tm(args), input)
+    }
+
+    /// Put a tmux on the `PATH` that keeps what it's asked in `tmux.log` and runs
+    /// `sessions`, given as `name windows attached` lines
+    ///
+    /// `display-message` prints `$STUB_DISPLAY` when that's set, anything other
+    /// than that or listing sessions or clients just succeeds
+    pub fn with_stub_tmux(&self, sessions: &[(&str, u32, u32)]) -> &Sandbox {
+        let listing: String = sessions
+            .iter()
+            .map(|(name, windows, attached)| {
+                format!("{}\\t{}\\t{}\\t100\\t50\\t\\n", name, windows, attached)
+            })
+            .collect();
+
+        let script = format!(
+            "#!/bin/sh\n\
+             echo \"$*\" >> '{log}'\n\
+             case \"$1\" in\n\
+             -V) echo 'tmux 3.3a' ;;\n\
+             ls|list-sessions) printf '{listing}' ;;\n\
+             display-message) [ -n \"$STUB_DISPLAY\" ] && echo \"$STUB_DISPLAY\" ;;\n\
+             esac\n\
+             exit 0\n",
+            log = self.log_path().display(),
+            listing = listing,
+        );
+
+        let tmux = self.root.join("bin").join("tmux");
+
+        fs::write(&tmux, script).unwrap();
+        fs::set_permissions(&tmux, fs::Permissions::from_mode(0o755)).unwrap();
+
+        self
+    }
+
+    fn log_path(&self) -> PathBuf {
+        self.root.join("tmux.log")
+    }
+
+    /// Every tmux command line the stub was run with, leaving out `-V`
+    pub fn tmux_calls(&self) -> Vec<String> {
+        fs::read_to_string(self.log_path())
+            .unwrap_or_default()
+            .lines()
+            .filter(|line| *line != "-V")
+            .map(String::from)
+            .collect()
+    }
+
+    pub fn path(&self, file: &str) -> PathBuf {
+        Path::new(&self.root).join(file)
+    }
+}
+
+impl Drop for Sandbox {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.root);
+    }
+}
+
+/// Run `tm`, set up by `Sandbox::tm` and maybe more, with `input` on stdin
+pub fn feed(mut tm: Command, input: &str) -> Output {
+    use std::io::Write;
+
+    let mut child = tm
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    // `tm` may well not read it all before exiting
+    let _ = child.stdin.take().unwrap().write_all(input.as_bytes());
+
+    child.wait_with_output().unwrap()
+}
+
+pub fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+pub fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
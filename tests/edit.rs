@@ -0,0 +1,33 @@
+mod common;
+
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+
+use common::{feed, stderr, Sandbox};
+
+#[test]
+fn edit_opens_the_editor_in_the_sessions_directory() {
+    let sandbox = Sandbox::new("edit");
+    let project = sandbox.path("project");
+    let editor = sandbox.path("bin/editor");
+
+    fs::create_dir(&project).unwrap();
+    fs::write(
+        &editor,
+        format!("#!/bin/sh\npwd > '{}'\n", sandbox.path("edited").display()),
+    )
+    .unwrap();
+    fs::set_permissions(&editor, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let mut tm = sandbox.with_stub_tmux(&[("work", 1, 0)]).tm(&[]);
+    tm.env("EDITOR", &editor).env("STUB_DISPLAY", &project);
+
+    let output = feed(tm, "e1\n");
+    let edited = fs::read_to_string(sandbox.path("edited")).unwrap_or_default();
+    assert_eq!(
+        edited.trim_end(),
+        fs::canonicalize(&project).unwrap().display().to_string(),
+        "{}",
+        stderr(&output)
+    );
+}
@@ -0,0 +1,47 @@
+mod common;
+
+use common::{stderr, Sandbox};
+
+#[test]
+fn unknown_flags_are_reported_as_json_when_asked() {
+    let sandbox = Sandbox::new("errors-json-clap");
+    let output = sandbox.run(&["--error-format", "json", "--no-such-flag"], "");
+
+    assert_eq!(output.status.code(), Some(2));
+    assert!(
+        stderr(&output).starts_with("{\"error\":"),
+        "{}",
+        stderr(&output)
+    );
+    assert!(
+        stderr(&output).contains("\"code\":2"),
+        "{}",
+        stderr(&output)
+    );
+}
+
+#[test]
+fn broken_tm_opts_are_reported_as_json_when_asked() {
+    let sandbox = Sandbox::new("errors-json-opts");
+    let output = sandbox
+        .tm(&["--error-format=json", "--list-names"])
+        .env("TM_OPTS", "--sort 'mru")
+        .output()
+        .unwrap();
+
+    assert_eq!(output.status.code(), Some(2));
+    assert!(
+        stderr(&output).starts_with("{\"error\":\"invalid TM_OPTS"),
+        "{}",
+        stderr(&output)
+    );
+}
+
+#[test]
+fn unknown_flags_are_plain_text_otherwise() {
+    let sandbox = Sandbox::new("errors-text-clap");
+    let output = sandbox.run(&["--no-such-flag"], "");
+
+    assert_eq!(output.status.code(), Some(2));
+    assert!(!stderr(&output).starts_with('{'), "{}", stderr(&output));
+}
@@ -0,0 +1,91 @@
+mod common;
+
+use common::{stderr, stdout, Sandbox};
+
+const SESSIONS: [(&str, u32, u32); 2] = [("work", 2, 0), ("play", 1, 0)];
+
+fn killed(sandbox: &Sandbox) -> Vec<String> {
+    sandbox
+        .tmux_calls()
+        .into_iter()
+        .filter(|call| call.starts_with("kill-"))
+        .collect()
+}
+
+#[test]
+fn kill_kills_the_session_named() {
+    let sandbox = Sandbox::new("kill-one");
+    let output = sandbox.with_stub_tmux(&SESSIONS).run(&["kill", "work"], "");
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(killed(&sandbox), ["kill-session -t =work"]);
+    assert_eq!(stdout(&output), "Killed work\n");
+}
+
+#[test]
+fn kill_refuses_a_session_that_isnt_running() {
+    let sandbox = Sandbox::new("kill-missing");
+    let output = sandbox.with_stub_tmux(&SESSIONS).run(&["kill", "wrok"], "");
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).contains("no session named wrok"));
+    assert!(killed(&sandbox).is_empty());
+}
+
+#[test]
+fn kill_all_stops_the_server_once_agreed() {
+    let sandbox = Sandbox::new("kill-all-yes");
+    let output = sandbox
+        .with_stub_tmux(&SESSIONS)
+        .run(&["kill", "--all"], "y\n");
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(killed(&sandbox), ["kill-server"]);
+    assert!(stdout(&output).contains("Killed the tmux server"));
+}
+
+#[test]
+fn kill_all_aborts_when_the_answer_is_no() {
+    let sandbox = Sandbox::new("kill-all-no");
+    let output = sandbox
+        .with_stub_tmux(&SESSIONS)
+        .run(&["kill", "--all"], "n\n");
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(killed(&sandbox).is_empty());
+    assert!(stdout(&output).contains("Nothing was killed."));
+}
+
+#[test]
+fn kill_all_aborts_on_an_empty_answer() {
+    let sandbox = Sandbox::new("kill-all-empty");
+    let output = sandbox
+        .with_stub_tmux(&SESSIONS)
+        .run(&["kill", "--all"], "\n");
+
+    assert!(killed(&sandbox).is_empty());
+    assert!(stdout(&output).contains("Nothing was killed."));
+}
+
+#[test]
+fn quiet_kill_prints_nothing() {
+    let sandbox = Sandbox::new("kill-quiet");
+    let output = sandbox
+        .with_stub_tmux(&SESSIONS)
+        .run(&["--quiet", "kill", "work"], "");
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(killed(&sandbox), ["kill-session -t =work"]);
+    assert_eq!(stdout(&output), "");
+}
+
+#[test]
+fn quiet_kill_all_doesnt_say_nothing_was_killed() {
+    let sandbox = Sandbox::new("kill-all-quiet");
+    let output = sandbox
+        .with_stub_tmux(&SESSIONS)
+        .run(&["--quiet", "kill", "--all"], "n\n");
+
+    assert!(killed(&sandbox).is_empty());
+    assert!(!stdout(&output).contains("Nothing was killed."));
+}
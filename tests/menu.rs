@@ -0,0 +1,78 @@
+mod common;
+
+use common::{stderr, stdout, Sandbox};
+
+const SESSIONS: [(&str, u32, u32); 2] = [("work", 2, 0), ("play", 1, 0)];
+
+const HINT: &str = "1-2 or a name attach, r<number> rename, k<number> kill, n new, q quit";
+
+#[test]
+fn menu_lists_the_sessions_and_explains_a_wrong_choice() {
+    let sandbox = Sandbox::new("menu-wrong");
+    let output = sandbox.with_stub_tmux(&SESSIONS).run(&[], "9\n");
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        format!(
+            "1) work\n2) play\nYou didn't select an appropriate choice: {}\n",
+            HINT
+        )
+    );
+    assert_eq!(stderr(&output), "");
+}
+
+#[test]
+fn menu_narrows_the_list_to_the_sessions_matching() {
+    let sandbox = Sandbox::new("menu-narrow");
+    let output = sandbox.with_stub_tmux(&SESSIONS).run(&[], "or\n");
+
+    // the input runs out at the narrowed list, with no default to fall back on
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stdout(&output), "1) work\n2) play\n1) work\n");
+    assert_eq!(
+        stderr(&output),
+        "error: no input available, pipe in a choice or set default_selection\n"
+    );
+}
+
+#[test]
+fn menu_says_when_no_session_matches() {
+    let sandbox = Sandbox::new("menu-no-match");
+    let output = sandbox.with_stub_tmux(&SESSIONS).run(&[], "zzz\nq\n");
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        "1) work\n2) play\nNo sessions match 'zzz'\n1) work\n2) play\n"
+    );
+}
+
+#[test]
+fn menu_says_when_there_are_no_sessions() {
+    let sandbox = Sandbox::new("menu-empty");
+    let output = sandbox.with_stub_tmux(&[]).run(&[], "9\n");
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        "No existing tmux sessions found.\nYou didn't select an appropriate choice: n new, q quit\n"
+    );
+}
+
+#[test]
+fn menu_kills_a_session_once_confirmed() {
+    let sandbox = Sandbox::new("menu-kill");
+    let output = sandbox.with_stub_tmux(&SESSIONS).run(&[], "k2\ny\nq\n");
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    // the stub tmux still lists play afterwards
+    assert_eq!(
+        stdout(&output),
+        "1) work\n2) play\nKilled play\n1) work\n2) play\n"
+    );
+    assert!(sandbox
+        .tmux_calls()
+        .iter()
+        .any(|call| call.starts_with("kill-session")));
+}
@@ -0,0 +1,42 @@
+mod common;
+
+use common::{stderr, Sandbox};
+
+#[test]
+fn kill_all_without_tmux_says_so() {
+    let sandbox = Sandbox::new("missing-kill-all");
+    let output = sandbox.run(&["kill", "--all"], "y\n");
+
+    assert_eq!(output.status.code(), Some(127));
+    assert!(
+        stderr(&output).contains("tmux not found"),
+        "{}",
+        stderr(&output)
+    );
+}
+
+#[test]
+fn attach_without_tmux_says_so() {
+    let sandbox = Sandbox::new("missing-attach");
+    let output = sandbox.run(&["attach", "work"], "");
+
+    assert_eq!(output.status.code(), Some(127));
+    assert!(
+        stderr(&output).contains("tmux not found"),
+        "{}",
+        stderr(&output)
+    );
+}
+
+#[test]
+fn menu_without_tmux_says_so() {
+    let sandbox = Sandbox::new("missing-menu");
+    let output = sandbox.run(&[], "1\n");
+
+    assert_eq!(output.status.code(), Some(127));
+    assert!(
+        stderr(&output).contains("tmux not found"),
+        "{}",
+        stderr(&output)
+    );
+}
@@ -0,0 +1,40 @@
+mod common;
+
+use common::{stderr, Sandbox};
+
+const SESSIONS: [(&str, u32, u32); 1] = [("work", 2, 0)];
+
+#[test]
+fn select_window_by_name_refuses_a_session_that_isnt_running() {
+    let sandbox = Sandbox::new("windows-missing");
+    let output = sandbox
+        .with_stub_tmux(&SESSIONS)
+        .run(&["--select-window-by-name", "wrok", "editor"], "");
+
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stderr(&output), "error: no session named wrok\n");
+    assert!(sandbox
+        .tmux_calls()
+        .iter()
+        .all(|call| !call.starts_with("list-windows")));
+}
+
+#[test]
+fn select_window_by_name_looks_up_the_exact_session() {
+    let sandbox = Sandbox::new("windows-exact");
+    let output = sandbox
+        .with_stub_tmux(&SESSIONS)
+        .run(&["--select-window-by-name", "work", "editor"], "");
+
+    // the stub tmux has no windows to list
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stderr(&output), "error: work has no window named editor\n");
+    assert!(
+        sandbox
+            .tmux_calls()
+            .iter()
+            .any(|call| call.starts_with("list-windows -t =work ")),
+        "{:?}",
+        sandbox.tmux_calls()
+    );
+}